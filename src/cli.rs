@@ -1,2 +1,3 @@
 pub(crate) mod interface;
+pub(crate) mod picker;
 pub(crate) mod terminal;
@@ -4,13 +4,24 @@ mod interface_test;
 
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::cli::terminal;
-use crate::config::{CONFIG_FILE, locate_config, parse_config, set_cwd_to_config_dir};
+use crate::cli::picker::{self, PickerEntry};
+use crate::cli::terminal::{self, ColorChoice, OutputFormat};
+use crate::config::{
+    AlchemistConfig, CONFIG_FILE, STDIN_CONFIG, global_config_path, locate_config, parse_config,
+    set_cwd_to_config_dir,
+};
 use crate::error::{AssertionError, Result, ResultContext};
-use crate::tasks::{RunnableTask, TaskDescription};
-use clap::{CommandFactory, Parser};
+use crate::process;
+use crate::report;
+use crate::tasks::{AlchemistTaskType, RunnableTask, TaskDescription, skip_tasks};
+use crate::watch::{self, WatchFilter};
+use clap::Parser;
+use clap_complete::Shell;
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::Shells;
+use indexmap::IndexMap;
 use owo_colors::OwoColorize;
 use terminal_size::{Height, Width, terminal_size};
 use unicode_segmentation::UnicodeSegmentation;
@@ -24,14 +35,29 @@ pub(crate) struct CliArgs {
     #[arg(short, long, help="Lists all available commands in the current project. Use -v(v) for more detailed output\n    -v\tShow what each task does\n    -vv\tExpand ShellScriptTasks", conflicts_with_all=["init", "shell_complete", "commands"])]
     pub list: bool,
 
-    #[arg(short, long, action = clap::ArgAction::Count, hide = true, conflicts_with_all=["init", "shell_complete", "commands"])]
+    #[arg(
+        long,
+        help = "Show the fully resolved definition of the given tasks (with everything they extend) instead of running them",
+        conflicts_with_all=["list", "init", "shell_complete", "watch", "workspace"],
+        requires = "commands"
+    )]
+    pub explain: bool,
+
+    #[arg(short, long, action = clap::ArgAction::Count, help = "Show more details. With --list what tasks do, while running the working directory and environment of every command\n    -v\tShow the environment variables set by tasks\n    -vv\tAlso show the inherited environment", conflicts_with_all=["init", "shell_complete"])]
     pub verbose: u8,
 
     #[arg(short, long, help = "Write an alchemist example file to start a new alchemist project", conflicts_with_all=["list", "shell_complete", "commands"])]
     pub init: Option<Option<PathBuf>>,
 
-    #[arg(short, long, help= "Writes completion files to common shells", conflicts_with_all=["list", "init", "commands"])]
-    pub shell_complete: bool,
+    #[arg(short, long, value_name = "SHELL", help = "Writes the completion file for the given shell (defaults to $SHELL, or fish)", conflicts_with_all=["list", "init", "commands"])]
+    pub shell_complete: Option<Option<Shell>>,
+
+    #[arg(
+        long,
+        help = "Print the shell completions to stdout instead of writing them to a file",
+        requires = "shell_complete"
+    )]
+    pub stdout: bool,
 
     #[arg(
         short,
@@ -40,28 +66,283 @@ pub(crate) struct CliArgs {
     )]
     pub quiet: bool,
 
-    #[arg(conflicts_with_all=["list", "init", "shell_complete"])]
+    #[arg(
+        long,
+        help = "Only show the output of tasks and errors, nothing else alchemist prints",
+        conflicts_with = "list"
+    )]
+    pub silent: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorChoice::Auto,
+        help = "When to color the output (`auto` honors NO_COLOR and only colors terminals)"
+    )]
+    pub color: ColorChoice,
+
+    #[arg(
+        short,
+        long,
+        help = "Run the given tasks in every member project of the workspace",
+        conflicts_with_all=["list", "init", "shell_complete"]
+    )]
+    pub workspace: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Run the workspace members in parallel instead of one after the other",
+        requires = "workspace"
+    )]
+    pub parallel: bool,
+
+    #[arg(
+        short,
+        long,
+        env = "ALCHEMIST_FILE",
+        help = "Use the given config file instead of searching for one (`-` reads from stdin)"
+    )]
+    pub file: Option<PathBuf>,
+
+    #[arg(
+        short = 'C',
+        long,
+        help = "Change to the given directory before locating the config file"
+    )]
+    pub directory: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Run the given tasks again whenever files in the project change (see the `watch` task field)",
+        conflicts_with_all=["list", "init", "shell_complete", "workspace"],
+        requires = "commands"
+    )]
+    pub watch: bool,
+
+    #[arg(
+        long,
+        help = "Also react to changes in files ignored by .gitignore in watch mode",
+        requires = "watch"
+    )]
+    pub no_ignore: bool,
+
+    #[arg(
+        short = 'I',
+        long,
+        help = "Pick the tasks to run from a list (default when no tasks are given in a terminal)",
+        conflicts_with_all=["list", "init", "shell_complete", "commands"]
+    )]
+    pub interactive: bool,
+
+    #[arg(
+        long,
+        help = "Do not load the global tasks from ~/.config/alchemist/alchemist.toml"
+    )]
+    pub no_global: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        help = "How to report progress, `json` prints one event per line for CI and editors"
+    )]
+    pub output: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write the results of all tasks to the given file as a JUnit XML report"
+    )]
+    pub report_junit: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Also write the output of every task to <DIR>/<task>.log"
+    )]
+    pub log_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Prefix every line in log files with the time since the task started"
+    )]
+    pub log_timestamps: bool,
+
+    #[arg(
+        long,
+        help = "Highlight the critical path through parallel tasks in the run summary"
+    )]
+    pub timings: bool,
+
+    #[arg(conflicts_with_all=["list", "init", "shell_complete"], add = ArgValueCandidates::new(task_name_candidates))]
     pub commands: Vec<String>,
 }
 
-pub(crate) fn run_tasks(tasks: Vec<String>) -> Result<()> {
-    let config_file_path = locate_config()?;
+/// Locates and parses the config file, including the global tasks unless disabled
+fn load_config(args: &CliArgs) -> Result<(PathBuf, AlchemistConfig)> {
+    load_config_file(args.file.clone(), args.no_global)
+}
+
+fn load_config_file(
+    config_file: Option<PathBuf>,
+    no_global: bool,
+) -> Result<(PathBuf, AlchemistConfig)> {
+    let config_file_path = locate_config(config_file)?;
+    let mut alchemist_config = parse_config(&config_file_path)?;
+    if !no_global
+        && let Some(global_config_path) = global_config_path()
+        && global_config_path.is_file()
+    {
+        alchemist_config.merge_global_config(&global_config_path)?;
+    }
+    alchemist_config.check_tasks()?;
+    Ok((config_file_path, alchemist_config))
+}
+
+/// Lets the user pick the tasks to run from the visible tasks of the config
+pub(crate) fn pick_tasks(args: &CliArgs) -> Result<Vec<String>> {
+    let (_, alchemist_config) = load_config(args)?;
+    let entries = alchemist_config
+        .tasks
+        .iter()
+        .filter(|(_, task)| task.is_shown())
+        .map(|(name, task)| PickerEntry {
+            name: name.clone(),
+            description: task.describe(),
+        })
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        terminal::warn("No tasks configured!");
+        return Ok(Vec::new());
+    }
+    picker::pick_tasks(&entries, std::io::stdin().lock(), std::io::stdout())
+}
+
+pub(crate) fn run_tasks(args: &CliArgs) -> Result<()> {
+    let (config_file_path, alchemist_config) = load_config(args)?;
     terminal::info(format!(
         "Using alchemist file: {}",
         config_file_path.display()
     ));
-    let alchemist_config = parse_config(&config_file_path)?;
     set_cwd_to_config_dir(&config_file_path)?;
 
-    for t in tasks {
-        match alchemist_config.tasks.get(&t) {
-            Some(task) => {
-                task.run(t, &alchemist_config)?;
+    let result = args.commands.iter().enumerate().try_for_each(|(i, t)| {
+        let result = match alchemist_config.resolve_task(t)? {
+            Some((task_config, task)) => task.run(alchemist_config.task_name(t), &task_config),
+            None => {
+                terminal::warn(format!("Task '{}' does not exist!", t));
+                Ok(())
             }
-            None => terminal::warn(format!("Task '{}' does not exist!", t)),
+        };
+        if result.is_err() {
+            skip_tasks(&args.commands[i + 1..], &alchemist_config);
+        }
+        result
+    });
+    process::stop_services();
+    result
+}
+
+/// Runs the given tasks, and runs them again every time a watched file changes
+pub(crate) fn watch_tasks(args: &CliArgs) -> Result<()> {
+    let (config_file_path, alchemist_config) = load_config(args)?;
+    set_cwd_to_config_dir(&config_file_path)?;
+    let root = std::env::current_dir().error_msg("Cannot access the current directory")?;
+
+    let patterns = args
+        .commands
+        .iter()
+        .filter_map(|t| alchemist_config.task(t))
+        .filter_map(|task| task.watch_patterns())
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    let filter = WatchFilter::new(&root, &patterns, !args.no_ignore)?;
+    terminal::info(format!(
+        "Watching {} for changes{}",
+        root.display(),
+        if patterns.is_empty() {
+            String::new()
+        } else {
+            format!(" to {}", patterns.join(", "))
         }
+    ));
+    watch::watch(filter, || {
+        let result = run_tasks(args);
+        report::finish_run();
+        report::reset();
+        result
+    })
+}
+
+pub(crate) fn run_workspace_tasks(args: &CliArgs) -> Result<()> {
+    let result = run_workspace_members(args);
+    process::stop_services();
+    result
+}
+
+fn run_workspace_members(args: &CliArgs) -> Result<()> {
+    let (config_file_path, alchemist_config) = load_config(args)?;
+    set_cwd_to_config_dir(&config_file_path)?;
+    let tasks = &args.commands;
+
+    let members = alchemist_config.workspace_members()?;
+    if members.is_empty() {
+        terminal::warn("No workspace members found!");
+        return Ok(());
+    }
+
+    let root = alchemist_config.project_dir.unwrap_or_default();
+    let run_member = |member: PathBuf, tasks: Vec<String>| -> Result<()> {
+        let (_, member_config) = load_config_file(Some(member.join(CONFIG_FILE)), args.no_global)?;
+        let member_name = member.strip_prefix(&root).unwrap_or(&member).display();
+        terminal::info(format!("Running tasks in workspace member {}", member_name));
+        for t in tasks {
+            match member_config.task(&t) {
+                Some(task) => task.run(
+                    format!("{}:{}", member_name, member_config.task_name(&t)),
+                    &member_config,
+                )?,
+                None => terminal::warn(format!(
+                    "Task '{}' does not exist in workspace member {}, skipping.",
+                    t, member_name
+                )),
+            }
+        }
+        Ok(())
+    };
+
+    if !args.parallel {
+        for member in members {
+            run_member(member, tasks.clone())?;
+        }
+        return Ok(());
+    }
+
+    let mut has_error = false;
+    std::thread::scope(|scope| {
+        let background_jobs = members
+            .into_iter()
+            .map(|member| {
+                let tasks = tasks.clone();
+                scope.spawn(|| run_member(member, tasks))
+            })
+            .collect::<Vec<_>>();
+        for job in background_jobs {
+            if let Err(e) = job.join().expect("Can not join thread") {
+                terminal::error(e);
+                has_error = true;
+            }
+        }
+    });
+    if has_error {
+        AssertionError("One or more errors occoured in workspace members".into()).into()
+    } else {
+        Ok(())
     }
-    Ok(())
 }
 
 pub(crate) fn create_template_config(target: Option<PathBuf>) -> Result<()> {
@@ -95,21 +376,107 @@ pub(crate) fn create_template_config(target: Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-pub(crate) fn generate_completions() {
-    let home = PathBuf::from(std::env::var("HOME").expect("could not determine home dir"));
-    let completion_dir = home.join(".config").join("fish").join("completions");
+/// Completion file for `shell` (relative to the home dir) and how to enable it
+fn completion_file(shell: Shell) -> Result<(PathBuf, Option<&'static str>)> {
+    let (path, hint) = match shell {
+        Shell::Bash => (".local/share/bash-completion/completions/alchemist", None),
+        Shell::Elvish => (
+            ".config/elvish/lib/alchemist.elv",
+            Some("Add `use alchemist` to your ~/.config/elvish/rc.elv"),
+        ),
+        Shell::Fish => (".config/fish/completions/alchemist.fish", None),
+        Shell::PowerShell => (
+            ".config/powershell/alchemist.ps1",
+            Some("Add `. ~/.config/powershell/alchemist.ps1` to your $PROFILE"),
+        ),
+        Shell::Zsh => (
+            ".zfunc/_alchemist",
+            Some("Add `fpath+=~/.zfunc; autoload -Uz compinit; compinit` to your ~/.zshrc"),
+        ),
+        _ => {
+            return AssertionError(format!("Completions for {shell} are not supported.")).into();
+        }
+    };
+    Ok((PathBuf::from(path), hint))
+}
+
+/// Writes (or prints) the completion script for `shell`
+///
+/// The script calls back into alchemist while completing, so task names are
+/// always read from the nearest config file.
+pub(crate) fn generate_completions(shell: Option<Shell>, stdout: bool) -> Result<()> {
+    let shell = shell.or_else(Shell::from_env).unwrap_or(Shell::Fish);
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| AssertionError(format!("Completions for {shell} are not supported.")))?;
+
+    let mut script = Vec::new();
+    completer
+        .write_registration(
+            "COMPLETE",
+            "alchemist",
+            "alchemist",
+            "alchemist",
+            &mut script,
+        )
+        .error_msg("Could not generate the completions.")?;
+
+    if stdout {
+        std::io::stdout()
+            .write_all(&script)
+            .error_msg("Could not write the completions to stdout.")?;
+        return Ok(());
+    }
+
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| AssertionError("Could not determine the home directory.".into()))?;
+    let (relative_path, hint) = completion_file(shell)?;
+    let completion_path = home.join(relative_path);
+    if let Some(completion_dir) = completion_path.parent() {
+        std::fs::create_dir_all(completion_dir)
+            .error_msg("Could not create the completions directory.")?;
+    }
+    std::fs::write(&completion_path, script).error_msg("Could not write the completions file.")?;
+    terminal::ok(format!(
+        "Wrote {} completions to {}",
+        shell,
+        completion_path.display()
+    ));
+    if let Some(hint) = hint {
+        terminal::info(hint);
+    }
+    Ok(())
+}
 
-    if let Err(e) = std::fs::create_dir_all(&completion_dir) {
-        panic!("Error: {}", e);
+/// Names of the visible tasks in the nearest config, for dynamic shell completion
+fn task_name_candidates() -> Vec<CompletionCandidate> {
+    let config_file = std::env::var_os("ALCHEMIST_FILE").map(PathBuf::from);
+    // Reading the config from stdin would block the shell.
+    if config_file.as_deref() == Some(Path::new(STDIN_CONFIG)) {
+        return Vec::new();
     }
-    let mut cmd = CliArgs::command();
-    clap_complete::generate_to(
-        clap_complete::Shell::Fish,
-        &mut cmd,
-        "alchemist",
-        &completion_dir,
-    )
-    .expect("could not write completions file");
+    let Ok((_, alchemist_config)) = load_config_file(config_file, false) else {
+        return Vec::new();
+    };
+    alchemist_config
+        .tasks
+        .iter()
+        .filter(|(_, task)| task.is_shown())
+        .flat_map(|(name, task)| {
+            let description = task.describe();
+            let candidate = CompletionCandidate::new(name)
+                .help(Some(description.description.join(" ").into()))
+                .tag(Some(description.task_type.into()));
+            let aliases = task.aliases().iter().map(move |alias| {
+                CompletionCandidate::new(alias)
+                    .help(Some(format!("alias of {name}").into()))
+                    .tag(Some("alias".into()))
+            });
+            std::iter::once(candidate).chain(aliases)
+        })
+        .collect()
 }
 
 fn grapheme_length(s: &str) -> usize {
@@ -140,9 +507,34 @@ fn graphemes_in_range_safe(s: &str, start: Option<usize>, end: Option<usize>) ->
     result
 }
 
-pub(crate) fn list_available_tasks(verbose: u8) -> Result<()> {
-    let config_file_path = locate_config()?;
-    let alchemist_config = parse_config(&config_file_path)?;
+/// The `default` task of the project, run when no tasks are given
+pub(crate) fn default_task(args: &CliArgs) -> Result<Option<String>> {
+    let (_, alchemist_config) = load_config(args)?;
+    Ok(alchemist_config.default)
+}
+
+/// Prints the given tasks the way alchemist runs them, as TOML
+pub(crate) fn explain_tasks(args: &CliArgs) -> Result<()> {
+    let (_, alchemist_config) = load_config(args)?;
+    for task_name in &args.commands {
+        let Some((_, task)) = alchemist_config.resolve_task(task_name)? else {
+            terminal::warn(format!("Task '{}' does not exist!", task_name));
+            continue;
+        };
+        let definition = IndexMap::from([("tasks", IndexMap::from([(task_name, task)]))]);
+        let definition = toml::to_string(&definition).map_err(|e| {
+            AssertionError(format!(
+                "Can not show the definition of task '{task_name}' ({e})."
+            ))
+        })?;
+        terminal::print(definition);
+    }
+    Ok(())
+}
+
+pub(crate) fn list_available_tasks(args: &CliArgs) -> Result<()> {
+    let verbose = args.verbose;
+    let (_, alchemist_config) = load_config(args)?;
 
     if alchemist_config.tasks.is_empty() {
         terminal::warn("No tasks configured!");
@@ -154,14 +546,14 @@ pub(crate) fn list_available_tasks(verbose: u8) -> Result<()> {
         .tasks
         .iter()
         .filter(|(_, v)| v.is_shown() || verbose >= 1)
-        .map(|(k, v)| (k, v.describe()))
-        .collect::<Vec<(&String, TaskDescription)>>();
+        .map(|(k, v)| (k, v, v.describe()))
+        .collect::<Vec<(&String, &AlchemistTaskType, TaskDescription)>>();
 
-    println!(" ┌──────────────────┐");
-    println!(" │ Available tasks: │");
-    println!(" ├──────────────────┘");
+    terminal::print(" ┌──────────────────┐");
+    terminal::print(" │ Available tasks: │");
+    terminal::print(" ├──────────────────┘");
     if verbose > 0 {
-        println!(" │");
+        terminal::print(" │");
     }
 
     let num_tasks = task_names.len();
@@ -171,18 +563,40 @@ pub(crate) fn list_available_tasks(verbose: u8) -> Result<()> {
         _ => TERMINAL_WIDTH_DEFAULT,
     } - INDENT_TASK_CONTENT;
 
-    for (i, (task_name, description)) in task_names.into_iter().enumerate() {
+    for (i, (task_name, task, description)) in task_names.into_iter().enumerate() {
         let (entry_prefix, desc_prefix) = if i == num_tasks - 1 {
             (" └", "  ")
         } else {
             (" ├", " │")
         };
-        println!(
-            "{} {} · {}",
+        let origin = if alchemist_config.global_tasks.contains(task_name) {
+            format!(" · {}", "global".cyan())
+        } else {
+            String::new()
+        };
+        let default = if alchemist_config
+            .default
+            .as_deref()
+            .is_some_and(|default| alchemist_config.task_name(default) == task_name)
+        {
+            format!(" · {}", "default".green())
+        } else {
+            String::new()
+        };
+        let aliases = if task.aliases().is_empty() {
+            String::new()
+        } else {
+            format!(" · {} {}", "aliases:".dimmed(), task.aliases().join(", "))
+        };
+        terminal::print(format!(
+            "{} {} · {}{}{}{}",
             entry_prefix,
             task_name.bold(),
-            description.task_type.yellow()
-        );
+            description.task_type.yellow(),
+            origin,
+            default,
+            aliases
+        ));
         let desc = match verbose {
             0 => continue,
             1 => match description.description.len() {
@@ -202,18 +616,21 @@ pub(crate) fn list_available_tasks(verbose: u8) -> Result<()> {
             let line_len = grapheme_length(&line);
             if line_len > usable_terminal_width {
                 let line_part_len = (usable_terminal_width - 5) / 2;
-                println!(
+                terminal::print(format!(
                     "{}    {}{}{}",
                     desc_prefix,
                     graphemes_in_range_safe(&line, None, Some(line_part_len)),
                     " ... ".blue(),
                     graphemes_in_range_safe(&line, Some(line_len - line_part_len), None)
-                );
+                ));
             } else {
-                println!("{}    {}", desc_prefix, line);
+                terminal::print(format!("{}    {}", desc_prefix, line));
             }
         }
-        println!("{}", desc_prefix);
+        for (instance_name, _) in task.matrix_instances(task_name).unwrap_or_default() {
+            terminal::print(format!("{}    {}", desc_prefix, instance_name.cyan()));
+        }
+        terminal::print(desc_prefix);
     }
 
     Ok(())
@@ -46,3 +46,16 @@ fn test_graphemes_in_range_safe() {
     assert_eq!(graphemes_in_range_safe(s, Some(0), Some(10)), "😀🥹🤣😇");
     assert_eq!(graphemes_in_range_safe(s, Some(10), Some(20)), "");
 }
+
+#[test]
+fn test_completions_for_every_shell() {
+    use clap::ValueEnum;
+
+    for shell in Shell::value_variants() {
+        assert!(completion_file(*shell).is_ok(), "no file for {shell}");
+        assert!(
+            Shells::builtins().completer(&shell.to_string()).is_some(),
+            "no dynamic completer for {shell}"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(test)]
+#[path = "picker_test.rs"]
+mod picker_test;
+
+use std::io::{BufRead, Write};
+
+use crate::cli::terminal;
+use crate::error::{Result, ResultContext};
+use crate::tasks::TaskDescription;
+use owo_colors::OwoColorize;
+
+/// A task that can be picked, with the description shown next to it
+pub struct PickerEntry {
+    pub name: String,
+    pub description: TaskDescription,
+}
+
+/// Whether all characters of `query` appear in `candidate`, in order
+///
+/// Matching ignores case, so `bld` matches `build` and `Rebuild`.
+pub fn fuzzy_match(query: &str, candidate: &str) -> bool {
+    let mut candidate_chars = candidate.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| candidate_chars.any(|c| c == q))
+}
+
+fn print_entries<W: Write>(entries: &[&PickerEntry], output: &mut W) -> Result<()> {
+    for (i, entry) in entries.iter().enumerate() {
+        let line = format!(
+            " {:>3}) {} · {}  {}",
+            (i + 1).blue(),
+            entry.name.bold(),
+            entry.description.task_type.yellow(),
+            entry
+                .description
+                .description
+                .first()
+                .map(String::as_str)
+                .unwrap_or_default()
+                .dimmed()
+        );
+        writeln!(output, "{}", terminal::styled(line))
+            .error_msg("Could not write the task list.")?;
+    }
+    Ok(())
+}
+
+/// Turns the words of `line` into task names, `None` if any word is not a selection
+fn parse_selection(line: &str, entries: &[&PickerEntry]) -> Option<Vec<String>> {
+    line.split_whitespace()
+        .map(|word| match word.parse::<usize>() {
+            Ok(i) if (1..=entries.len()).contains(&i) => Some(entries[i - 1].name.clone()),
+            Ok(_) => None,
+            Err(_) => entries
+                .iter()
+                .find(|e| e.name == word)
+                .map(|e| e.name.clone()),
+        })
+        .collect()
+}
+
+/// Lets the user pick one or more tasks
+///
+/// Every line of input either selects tasks (by number or by name, separated
+/// by spaces) or filters the list. An empty line cancels the selection.
+pub fn pick_tasks<R: BufRead, W: Write>(
+    entries: &[PickerEntry],
+    mut input: R,
+    mut output: W,
+) -> Result<Vec<String>> {
+    let mut shown = entries.iter().collect::<Vec<_>>();
+    loop {
+        print_entries(&shown, &mut output)?;
+        write!(
+            output,
+            "{} ",
+            terminal::styled(
+                "Select tasks (numbers or names), type to filter or press enter to cancel:".bold()
+            )
+        )
+        .error_msg("Could not write the prompt.")?;
+        output.flush().error_msg("Could not write the prompt.")?;
+
+        let mut line = String::new();
+        if input
+            .read_line(&mut line)
+            .error_msg("Could not read the selection.")?
+            == 0
+        {
+            return Ok(Vec::new());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(selection) = parse_selection(line, &shown) {
+            return Ok(selection);
+        }
+
+        shown = entries
+            .iter()
+            .filter(|e| {
+                fuzzy_match(line, &e.name)
+                    || fuzzy_match(line, &e.description.description.join(" "))
+            })
+            .collect();
+        if shown.is_empty() {
+            writeln!(output, "No tasks match '{}', showing all tasks.", line)
+                .error_msg("Could not write the task list.")?;
+            shown = entries.iter().collect();
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+fn entries() -> Vec<PickerEntry> {
+    ["build", "rebuild", "test"]
+        .into_iter()
+        .map(|name| PickerEntry {
+            name: name.to_string(),
+            description: TaskDescription {
+                task_type: "command".to_string(),
+                description: vec![format!("cargo {name}")],
+            },
+        })
+        .collect()
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert!(fuzzy_match("bld", "build"));
+    assert!(fuzzy_match("BLD", "Rebuild"));
+    assert!(fuzzy_match("", "anything"));
+    assert!(!fuzzy_match("dlb", "build"));
+    assert!(!fuzzy_match("tests", "test"));
+}
+
+#[test]
+fn test_pick_by_number_and_name() {
+    let picked = pick_tasks(&entries(), "3 build\n".as_bytes(), Vec::new()).unwrap();
+    assert_eq!(picked, vec!["test", "build"]);
+}
+
+#[test]
+fn test_pick_after_filtering() {
+    // After filtering on `reb` only `rebuild` is left, so it is number 1.
+    let picked = pick_tasks(&entries(), "reb\n1\n".as_bytes(), Vec::new()).unwrap();
+    assert_eq!(picked, vec!["rebuild"]);
+}
+
+#[test]
+fn test_pick_cancelled() {
+    assert!(
+        pick_tasks(&entries(), "\n".as_bytes(), Vec::new())
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        pick_tasks(&entries(), "".as_bytes(), Vec::new())
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -1,4 +1,18 @@
+#[cfg(test)]
+#[path = "terminal_test.rs"]
+mod terminal_test;
+
+use std::ffi::OsStr;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{LazyLock, OnceLock};
+
 use owo_colors::OwoColorize;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::report::TaskStatus;
 
 pub const INFO: &str = "ℹ︎";
 pub const ERROR: &str = "✘";
@@ -7,6 +21,264 @@ pub const WARNING: &str = "‼︎";
 #[cfg(debug_assertions)]
 pub const DEBUG: &str = "⌗";
 
+/// How alchemist reports what it is doing
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Colored messages for humans
+    #[default]
+    Text,
+    /// One JSON event per line, the output of tasks included
+    Json,
+}
+
+/// When to color the output of alchemist
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    /// Color when printing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(&self) -> bool {
+        self.enabled_for(
+            std::env::var_os("NO_COLOR").as_deref(),
+            std::io::stdout().is_terminal(),
+        )
+    }
+
+    /// Whether to color, given the value of `NO_COLOR` and whether stdout is a terminal
+    fn enabled_for(&self, no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color.is_none_or(|v| v.is_empty()) && is_terminal,
+        }
+    }
+}
+
+/// How everything alchemist prints is shown
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub format: OutputFormat,
+    pub color: ColorChoice,
+    /// Show the working directory and environment of commands (`-v`), including
+    /// the inherited environment (`-vv`)
+    pub verbosity: u8,
+    /// Only show the output of tasks and errors
+    pub silent: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Info,
+    Ok,
+    Warn,
+    Error,
+    Debug,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Everything alchemist reports while running
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Message {
+        level: Level,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        kind: Option<&'a str>,
+        message: String,
+    },
+    TaskStarted {
+        id: usize,
+        parent: Option<usize>,
+        task: &'a str,
+        task_type: &'a str,
+    },
+    CommandSpawned {
+        task: &'a str,
+        command: String,
+        pid: u32,
+    },
+    OutputLine {
+        task: &'a str,
+        stream: Stream,
+        line: &'a str,
+    },
+    TaskFinished {
+        id: usize,
+        parent: Option<usize>,
+        task: &'a str,
+        task_type: &'a str,
+        status: TaskStatus,
+        duration_ms: u128,
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+    },
+    RunFinished {
+        success: bool,
+    },
+}
+
+/// Receives every [`Event`], deciding how to show it
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: &Event);
+}
+
+/// Prints messages and the output of tasks for humans, ignores other events
+struct TextReporter {
+    /// Only show the output of tasks and errors
+    silent: bool,
+}
+
+impl TextReporter {
+    /// The line `event` is shown as and the stream it goes to, if any
+    fn render(&self, event: &Event) -> Option<(Stream, String)> {
+        match event {
+            Event::Message {
+                level: Level::Error,
+                kind,
+                message,
+            } => Some((
+                Stream::Stderr,
+                styled(format!(
+                    "{}{}{}{}{}",
+                    error_prefix(),
+                    "[".dimmed(),
+                    kind.unwrap_or("Error").dimmed().italic(),
+                    "]: ".dimmed(),
+                    message
+                )),
+            )),
+            Event::Message { .. } if self.silent => None,
+            Event::Message { level, message, .. } => {
+                let icon = match level {
+                    Level::Info => INFO.cyan().bold().to_string(),
+                    Level::Ok => OK.green().bold().to_string(),
+                    Level::Warn => WARNING.yellow().bold().to_string(),
+                    #[cfg(debug_assertions)]
+                    Level::Debug => DEBUG.magenta().bold().to_string(),
+                    _ => return None,
+                };
+                Some((
+                    Stream::Stdout,
+                    styled(format!(
+                        "{}{}{}",
+                        message_prefix(icon),
+                        ": ".dimmed(),
+                        message
+                    )),
+                ))
+            }
+            Event::OutputLine { stream, line, .. } => Some((*stream, line.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl Reporter for TextReporter {
+    fn report(&self, event: &Event) {
+        match self.render(event) {
+            Some((Stream::Stdout, line)) => println!("{line}"),
+            Some((Stream::Stderr, line)) => eprintln!("{line}"),
+            None => {}
+        }
+    }
+}
+
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("\x1b\\[[0-9;]*m").expect("valid regex"));
+
+/// Removes the color codes from `text`
+pub fn strip_ansi(text: &str) -> String {
+    ANSI_ESCAPE.replace_all(text, "").into_owned()
+}
+
+/// Prints every event as a line of JSON, without colors
+struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn report(&self, event: &Event) {
+        let json = match event {
+            Event::Message {
+                level,
+                kind,
+                message,
+            } => serde_json::to_string(&Event::Message {
+                level: *level,
+                kind: *kind,
+                message: strip_ansi(message),
+            }),
+            event => serde_json::to_string(event),
+        };
+        if let Ok(json) = json {
+            println!("{json}");
+        }
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static COLORS_ENABLED: OnceLock<bool> = OnceLock::new();
+static REPORTER: OnceLock<Box<dyn Reporter>> = OnceLock::new();
+
+/// Chooses how everything is shown, only the first call has any effect
+pub fn configure(settings: Settings) {
+    let format = settings.format;
+    let silent = settings.silent;
+    let _ = COLORS_ENABLED.set(settings.color.enabled());
+    if SETTINGS.set(settings).is_ok() {
+        let _ = REPORTER.set(match format {
+            OutputFormat::Text => Box::new(TextReporter { silent }),
+            OutputFormat::Json => Box::new(JsonReporter),
+        });
+    }
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+pub fn output_format() -> OutputFormat {
+    settings().format
+}
+
+pub fn verbosity() -> u8 {
+    settings().verbosity
+}
+
+pub fn is_silent() -> bool {
+    settings().silent
+}
+
+/// `text` as is when colors are enabled, without its color codes otherwise
+pub fn styled<S: ToString>(text: S) -> String {
+    if *COLORS_ENABLED.get_or_init(|| ColorChoice::Auto.enabled()) {
+        text.to_string()
+    } else {
+        strip_ansi(&text.to_string())
+    }
+}
+
+/// Prints `text` to stdout, see [`styled`]
+pub fn print<S: ToString>(text: S) {
+    println!("{}", styled(text))
+}
+
+pub fn event(event: Event) {
+    REPORTER
+        .get_or_init(|| Box::new(TextReporter { silent: false }))
+        .report(&event);
+}
+
 fn message_prefix<S: ToString>(icon: S) -> String {
     format!("{}{}{}", "[".dimmed(), icon.to_string(), "]".dimmed())
 }
@@ -15,43 +287,86 @@ pub fn error_prefix() -> String {
     message_prefix(ERROR.red().bold())
 }
 
+fn message<S: ToString>(level: Level, message: S) {
+    event(Event::Message {
+        level,
+        kind: None,
+        message: message.to_string(),
+    });
+}
+
 pub fn ok<S: ToString>(message: S) {
-    println!(
-        "{}{}{}",
-        message_prefix(OK.green().bold()),
-        ": ".dimmed(),
-        message.to_string()
-    );
+    self::message(Level::Ok, message)
 }
+
 pub fn warn<S: ToString>(message: S) {
-    println!(
-        "{}{}{}",
-        message_prefix(WARNING.yellow().bold()),
-        ": ".dimmed(),
-        message.to_string()
-    );
+    self::message(Level::Warn, message)
 }
 
 pub fn error(err: crate::error::AlchemistError) {
-    eprintln!("{}", err)
+    event(Event::Message {
+        level: Level::Error,
+        kind: Some(err.kind()),
+        message: err.message(),
+    })
 }
 
 pub fn info<S: ToString>(message: S) {
-    println!(
-        "{}{}{}",
-        message_prefix(INFO.cyan().bold()),
-        ": ".dimmed(),
-        message.to_string()
-    )
+    self::message(Level::Info, message)
 }
 
 #[allow(unused_variables)]
 pub fn debug<S: ToString>(message: S) {
     #[cfg(debug_assertions)]
-    println!(
-        "{}{}{}",
-        message_prefix(DEBUG.magenta().bold()),
-        ": ".dimmed(),
-        message.to_string()
-    )
+    self::message(Level::Debug, message)
+}
+
+/// Shows `message` when at least `level` verbose flags were given
+pub fn verbose<S: ToString>(level: u8, message: S) {
+    if verbosity() >= level {
+        info(message)
+    }
+}
+
+/// What `-v` shows about where and with which environment `cmd` runs, the
+/// inherited environment included from `-vv` on
+pub fn command_details(cmd: &Command, verbosity: u8) -> Vec<String> {
+    let mut lines = Vec::new();
+    if verbosity == 0 {
+        return lines;
+    }
+    if let Some(cwd) = cmd
+        .get_current_dir()
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+    {
+        lines.push(format!("  cwd: {}", cwd.display()));
+    }
+    let mut set_vars = Vec::new();
+    for (key, value) in cmd.get_envs() {
+        set_vars.push(key.to_os_string());
+        lines.push(match value {
+            Some(value) => format!("  env: {}={}", key.display(), value.display()),
+            None => format!("  env: {} (removed)", key.display()),
+        });
+    }
+    if verbosity >= 2 {
+        let mut inherited = std::env::vars_os()
+            .filter(|(key, _)| !set_vars.contains(key))
+            .collect::<Vec<_>>();
+        inherited.sort();
+        for (key, value) in inherited {
+            lines.push(format!(
+                "  inherited env: {}={}",
+                key.display(),
+                value.display()
+            ));
+        }
+    }
+    lines
+}
+
+/// Shows a line of output of the process of `task`
+pub fn output_line(task: &str, stream: Stream, line: &str) {
+    event(Event::OutputLine { task, stream, line })
 }
@@ -0,0 +1,116 @@
+use super::*;
+
+#[test]
+fn events_serialize_with_their_name() {
+    let event = Event::TaskFinished {
+        id: 2,
+        parent: Some(0),
+        task: "build",
+        task_type: "command",
+        status: TaskStatus::Failed,
+        duration_ms: 1500,
+        exit_code: Some(101),
+        signal: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"event":"task_finished","id":2,"parent":0,"task":"build","task_type":"command","status":"failed","duration_ms":1500,"exit_code":101,"signal":null}"#
+    );
+
+    let event = Event::OutputLine {
+        task: "build",
+        stream: Stream::Stderr,
+        line: "warning: unused variable",
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"event":"output_line","task":"build","stream":"stderr","line":"warning: unused variable"}"#
+    );
+}
+
+#[test]
+fn ansi_escapes_are_stripped() {
+    let message = format!("Using {}", "alchemist.toml".yellow());
+    assert_eq!(strip_ansi(&message), "Using alchemist.toml");
+}
+
+#[test]
+fn color_choice_enabled() {
+    let no_color = Some(OsStr::new("1"));
+    assert!(ColorChoice::Always.enabled_for(no_color, false));
+    assert!(!ColorChoice::Never.enabled_for(None, true));
+
+    assert!(ColorChoice::Auto.enabled_for(None, true));
+    assert!(ColorChoice::Auto.enabled_for(Some(OsStr::new("")), true));
+    assert!(!ColorChoice::Auto.enabled_for(no_color, true));
+    assert!(!ColorChoice::Auto.enabled_for(None, false));
+}
+
+#[test]
+fn silent_only_shows_errors_and_output() {
+    let info = Event::Message {
+        level: Level::Info,
+        kind: None,
+        message: "Running task build".to_string(),
+    };
+    let error = Event::Message {
+        level: Level::Error,
+        kind: Some("Config"),
+        message: "Unknown task".to_string(),
+    };
+    let output = Event::OutputLine {
+        task: "build",
+        stream: Stream::Stdout,
+        line: "compiling",
+    };
+
+    let reporter = TextReporter { silent: false };
+    assert_eq!(reporter.render(&info).unwrap().0, Stream::Stdout);
+    assert_eq!(reporter.render(&error).unwrap().0, Stream::Stderr);
+
+    let reporter = TextReporter { silent: true };
+    assert!(reporter.render(&info).is_none());
+    let (stream, line) = reporter.render(&error).unwrap();
+    assert_eq!(stream, Stream::Stderr);
+    assert!(strip_ansi(&line).ends_with("[Config]: Unknown task"));
+    assert_eq!(
+        reporter.render(&output),
+        Some((Stream::Stdout, "compiling".to_string()))
+    );
+}
+
+#[test]
+fn verbose_shows_cwd_and_env_of_commands() {
+    let mut cmd = Command::new("true");
+    cmd.current_dir("/tmp")
+        .env("PROFILE", "release")
+        .env_remove("DEBUG");
+
+    assert!(command_details(&cmd, 0).is_empty());
+    assert_eq!(
+        command_details(&cmd, 1),
+        [
+            "  cwd: /tmp",
+            "  env: DEBUG (removed)",
+            "  env: PROFILE=release"
+        ]
+    );
+
+    let details = command_details(&cmd, 2);
+    assert_eq!(details[..3], command_details(&cmd, 1));
+    assert!(
+        details[3..]
+            .iter()
+            .all(|l| l.starts_with("  inherited env: "))
+    );
+    assert!(
+        details
+            .iter()
+            .any(|l| l.starts_with("  inherited env: PATH="))
+    );
+    assert!(
+        !details
+            .iter()
+            .any(|l| l.contains("inherited env: PROFILE="))
+    );
+}
@@ -2,26 +2,41 @@
 #[path = "config_test.rs"]
 mod config_test;
 
+use std::borrow::Cow;
 use std::env::{current_dir, set_current_dir};
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use indexmap::IndexMap;
+use glob::{Pattern, glob};
+use indexmap::{IndexMap, IndexSet};
 
 use serde::Deserialize;
 
 use crate::cli::terminal;
-use crate::error::{AssertionError, Result, ResultContext};
+use crate::error::{AssertionError, ConfigError, Result, ResultContext, SourceLocation};
 use crate::tasks::*;
 
 pub const CONFIG_FILE: &str = "alchemist.toml";
+/// Optional (git-ignored) file next to the config that overrides parts of it
+pub const LOCAL_CONFIG_FILE: &str = "alchemist.local.toml";
+/// Config file path that makes alchemist read its config from stdin
+pub const STDIN_CONFIG: &str = "-";
+/// Directory (inside the user's config dir) that holds the global config file
+pub const GLOBAL_CONFIG_DIR: &str = "alchemist";
 
-#[derive(Debug, Deserialize, Clone)]
+/// The config read from stdin, which can only be read once
+static STDIN_CONTENT: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Deserialize, Clone, Default)]
 /// Contains the structure of the alchemist.toml file
 ///
 /// Reads a toml file like the following:
 /// ```
+/// workspace = ["services/*"]
+/// default = "task1"
+///
 /// [tasks.task1]
 /// ...
 ///
@@ -30,12 +45,175 @@ pub const CONFIG_FILE: &str = "alchemist.toml";
 /// ```
 pub struct AlchemistConfig {
     /// Contains a map of tasks that can be of multiple task types
+    #[serde(default)]
     pub tasks: IndexMap<String, AlchemistTaskType>,
+    /// Glob patterns (relative to this config) of nested alchemist projects
+    pub workspace: Option<Vec<String>>,
+    /// Task to run when alchemist is run without any tasks
+    pub default: Option<String>,
+    /// Directory containing the config file, tasks are executed from here
+    #[serde(skip)]
+    pub project_dir: Option<PathBuf>,
+    /// Names of the tasks that were merged in from the global config
+    #[serde(skip)]
+    pub global_tasks: IndexSet<String>,
+    /// The files this config was read from, for errors found after parsing
+    #[serde(skip)]
+    pub(crate) sources: Vec<ConfigSource>,
 }
 
-pub fn locate_config() -> Result<PathBuf> {
+impl AlchemistConfig {
+    /// Returns the directories of all workspace members that contain a config file
+    pub fn workspace_members(&self) -> Result<Vec<PathBuf>> {
+        let patterns = self.workspace.as_ref().ok_or_else(|| {
+            AssertionError(format!(
+                "No `workspace` members are declared in '{}'.",
+                CONFIG_FILE
+            ))
+        })?;
+        let root = self.project_dir.clone().unwrap_or_default();
+
+        let mut members = Vec::new();
+        for pattern in patterns {
+            let full_pattern = Path::new(&Pattern::escape(&root.to_string_lossy())).join(pattern);
+            let paths = glob(&full_pattern.to_string_lossy()).map_err(|e| {
+                AssertionError(format!("Invalid workspace pattern '{pattern}' ({e})."))
+            })?;
+            for path in paths.flatten() {
+                if path.join(CONFIG_FILE).is_file() && !members.contains(&path) {
+                    members.push(path);
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    /// The name of the task called `name`, which is `name` itself unless it is an alias
+    pub fn task_name<'a>(&'a self, name: &'a str) -> &'a str {
+        if self.tasks.contains_key(name) {
+            return name;
+        }
+        self.tasks
+            .iter()
+            .find(|(_, task)| task.aliases().iter().any(|alias| alias == name))
+            .map_or(name, |(task_name, _)| task_name.as_str())
+    }
+
+    /// The task called `name` (or with `name` as an alias) in this config
+    pub fn task(&self, name: &str) -> Option<&AlchemistTaskType> {
+        self.tasks.get(self.task_name(name))
+    }
+
+    /// Looks up a task by name
+    ///
+    /// Names in the form `path/to/project:task` refer to a task in the
+    /// alchemist project at that path (relative to this config), in which case
+    /// the config of that project is returned alongside the task. A task of
+    /// this config named like that, e.g. `test:unit`, takes precedence. Names like
+    /// `build[target=x86_64]` refer to an instance of a matrix task.
+    pub fn resolve_task(
+        &self,
+        task_ref: &str,
+    ) -> Result<Option<(Cow<'_, AlchemistConfig>, AlchemistTaskType)>> {
+        if let Some((task_ref, values)) = matrix::parse_instance_name(task_ref) {
+            let task_name = if self.task(task_ref).is_some() {
+                self.task_name(task_ref)
+            } else {
+                task_ref.rsplit(':').next().unwrap_or(task_ref)
+            };
+            return Ok(self.resolve_task(task_ref)?.and_then(|(config, task)| {
+                task.matrix_instance(task_name, &values)
+                    .map(|instance| (config, instance))
+            }));
+        }
+        // Task names can contain a colon too (`test:unit`), they take precedence.
+        if let Some(task) = self.task(task_ref) {
+            return Ok(Some((Cow::Borrowed(self), task.clone())));
+        }
+        let Some((project, task_name)) = task_ref.rsplit_once(':') else {
+            return Ok(None);
+        };
+        let project_dir = self.project_dir.clone().unwrap_or_default().join(project);
+        if !project_dir.is_dir() {
+            return Ok(None);
+        }
+        let project_config = parse_config(&project_dir.join(CONFIG_FILE))?;
+        project_config.check_tasks()?;
+        Ok(project_config
+            .task(task_name)
+            .cloned()
+            .map(|task| (Cow::Owned(project_config), task)))
+    }
+
+    /// Adds the tasks of the global config, tasks of this config take precedence
+    pub fn merge_global_config(&mut self, global_config_path: &PathBuf) -> Result<()> {
+        let global_config = parse_config(global_config_path)?;
+        for (task_name, task) in global_config.tasks {
+            if !self.tasks.contains_key(&task_name) {
+                self.global_tasks.insert(task_name.clone());
+                self.tasks.insert(task_name, task);
+            }
+        }
+        self.sources.extend(global_config.sources);
+        Ok(())
+    }
+
+    /// Checks what depends on all tasks being known, once the global tasks are merged in
+    pub fn check_tasks(&self) -> Result<()> {
+        check_aliases(self, &self.sources)?;
+        check_default(self, &self.sources)
+    }
+}
+
+/// Location of the user-level config file with global tasks
+///
+/// This is `$XDG_CONFIG_HOME/alchemist/alchemist.toml`, falling back to
+/// `~/.config/alchemist/alchemist.toml`.
+pub fn global_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join(GLOBAL_CONFIG_DIR).join(CONFIG_FILE))
+}
+
+/// The config given on stdin, read on the first call and kept for later ones
+/// (e.g. every run in watch mode)
+fn read_stdin_config() -> Result<String> {
+    if let Some(content) = STDIN_CONTENT.get() {
+        return Ok(content.clone());
+    }
+    let content = std::io::read_to_string(std::io::stdin())
+        .error_msg("Could not read the config from stdin")?;
+    Ok(STDIN_CONTENT.get_or_init(|| content).clone())
+}
+
+fn is_stdin_config(config_file_path: &Path) -> bool {
+    config_file_path == Path::new(STDIN_CONFIG)
+}
+
+/// Finds the config file to use
+///
+/// An explicitly given `config_file` is used as-is (`-` meaning stdin),
+/// otherwise the current directory and its ancestors are searched.
+pub fn locate_config(config_file: Option<PathBuf>) -> Result<PathBuf> {
     let workingdir = current_dir().error_msg("Cannot access the current directory")?;
 
+    if let Some(config_file) = config_file {
+        if is_stdin_config(&config_file) {
+            return Ok(config_file);
+        }
+        let config_file = workingdir.join(config_file);
+        if !config_file.is_file() {
+            return AssertionError(format!(
+                "'{}' does not exist or is not a file.",
+                config_file.display()
+            ))
+            .into();
+        }
+        return Ok(config_file);
+    }
+
     workingdir
         .ancestors()
         .map(|p| p.to_path_buf().join(CONFIG_FILE))
@@ -49,19 +227,415 @@ pub fn locate_config() -> Result<PathBuf> {
         })
 }
 
+/// The name and content of a config file, used for error reporting
+#[derive(Debug, Clone)]
+pub(crate) struct ConfigSource {
+    file: String,
+    content: String,
+}
+
+impl ConfigSource {
+    fn parse(&self) -> Result<toml::Table> {
+        toml::from_str::<toml::Table>(&self.content).map_err(|e| {
+            ConfigError {
+                message: format!("Invalid configuration: {}", e.message().trim()),
+                file: self.file.clone(),
+                location: e
+                    .span()
+                    .map(|span| Box::new(SourceLocation::from_span(&self.content, span))),
+                notes: Vec::new(),
+            }
+            .into()
+        })
+    }
+}
+
 pub fn parse_config(config_file_path: &PathBuf) -> Result<AlchemistConfig> {
     terminal::debug(format!("searching for {}\n", CONFIG_FILE));
 
-    let config_file_content =
-        fs::read_to_string(config_file_path).error_msg("Could not read the config file")?;
-    let cfg = toml::from_str::<AlchemistConfig>(&config_file_content)
-        .error_msg("Invalid configuration.")?;
+    let mut sources = vec![if is_stdin_config(config_file_path) {
+        ConfigSource {
+            file: String::from("<stdin>"),
+            content: read_stdin_config()?,
+        }
+    } else {
+        ConfigSource {
+            file: config_file_path.display().to_string(),
+            content: fs::read_to_string(config_file_path)
+                .error_msg("Could not read the config file")?,
+        }
+    }];
+    let mut config_table = sources[0].parse()?;
+
+    if !is_stdin_config(config_file_path) {
+        let local_config_path = config_file_path.with_file_name(LOCAL_CONFIG_FILE);
+        if local_config_path.is_file() {
+            let local_source = ConfigSource {
+                file: local_config_path.display().to_string(),
+                content: fs::read_to_string(&local_config_path)
+                    .error_msg("Could not read the local config file")?,
+            };
+            merge_tables(&mut config_table, local_source.parse()?);
+            sources.push(local_source);
+        }
+    }
+
+    resolve_extends(&mut config_table, &sources)?;
+
+    let mut cfg = match config_table.clone().try_into::<AlchemistConfig>() {
+        Ok(cfg) => cfg,
+        Err(e) => return diagnose_config(&config_table, &sources, e).into(),
+    };
+    check_matrices(&cfg, &sources)?;
+    if !is_stdin_config(config_file_path) {
+        cfg.project_dir = config_file_path.parent().map(Path::to_path_buf);
+    }
+    cfg.sources = sources;
     Ok(cfg)
 }
 
+/// An error about `field` of the task `task_name`, pointing at that field
+fn task_error(
+    sources: &[ConfigSource],
+    task_name: &str,
+    field: &str,
+    message: String,
+) -> ConfigError {
+    let (file, location) = sources
+        .iter()
+        .find_map(|source| {
+            task_span(source, task_name, Some(field)).map(|span| {
+                (
+                    source.file.clone(),
+                    Some(Box::new(SourceLocation::from_span(&source.content, span))),
+                )
+            })
+        })
+        .unwrap_or_else(|| (sources[0].file.clone(), None));
+    ConfigError {
+        message,
+        file,
+        location,
+        notes: Vec::new(),
+    }
+}
+
+/// Makes sure every alias names a single task and is not the name of another task
+fn check_aliases(config: &AlchemistConfig, sources: &[ConfigSource]) -> Result<()> {
+    let mut aliases = IndexMap::<&str, &str>::new();
+    for (task_name, task) in &config.tasks {
+        for alias in task.aliases() {
+            let message = if config.tasks.contains_key(alias) {
+                format!("The alias '{alias}' of task '{task_name}' is already the name of a task.")
+            } else if let Some(other) = aliases.insert(alias, task_name) {
+                format!(
+                    "The alias '{alias}' of task '{task_name}' is already an alias of task '{other}'."
+                )
+            } else {
+                continue;
+            };
+            return Err(task_error(sources, task_name, "aliases", message).into());
+        }
+    }
+    Ok(())
+}
+
+/// Makes sure every matrix has axes and values that can be part of instance names
+fn check_matrices(config: &AlchemistConfig, sources: &[ConfigSource]) -> Result<()> {
+    for (task_name, task) in &config.tasks {
+        if let Some(matrix) = task.matrix()
+            && let Err(message) = matrix::check(task_name, matrix)
+        {
+            return Err(task_error(sources, task_name, "matrix", message).into());
+        }
+    }
+    Ok(())
+}
+
+/// Makes sure the `default` task exists
+fn check_default(config: &AlchemistConfig, sources: &[ConfigSource]) -> Result<()> {
+    let Some(default) = &config.default else {
+        return Ok(());
+    };
+    if config.task(default).is_some() {
+        return Ok(());
+    }
+    let (file, location) = sources
+        .iter()
+        .find_map(|source| {
+            let document = toml_edit::ImDocument::parse(source.content.as_str()).ok()?;
+            let (key, _) = document.get_key_value("default")?;
+            key.span().map(|span| {
+                (
+                    source.file.clone(),
+                    Some(Box::new(SourceLocation::from_span(&source.content, span))),
+                )
+            })
+        })
+        .unwrap_or_else(|| (sources[0].file.clone(), None));
+    Err(ConfigError {
+        message: format!("The default task '{default}' does not exist."),
+        file,
+        location,
+        notes: Vec::new(),
+    }
+    .into())
+}
+
+/// Why a task could not be extended, and the task whose `extends` is at fault
+struct ExtendsError {
+    message: String,
+    task_name: String,
+}
+
+/// Replaces every task with its definition including the fields of the task it extends
+fn resolve_extends(config_table: &mut toml::Table, sources: &[ConfigSource]) -> Result<()> {
+    let Some(tasks) = config_table.get("tasks").and_then(toml::Value::as_table) else {
+        return Ok(());
+    };
+    let mut resolved = toml::Table::new();
+    for task_name in tasks.keys() {
+        let task = extend_task(tasks, task_name, &mut Vec::new())
+            .map_err(|e| task_error(sources, &e.task_name, "extends", e.message))?;
+        resolved.insert(task_name.clone(), task);
+    }
+    config_table.insert("tasks".to_string(), toml::Value::Table(resolved));
+    Ok(())
+}
+
+/// The task `task_name` including the fields of the task it extends
+///
+/// Fields of the task replace inherited ones, except for `env` which is merged.
+/// `append_args` is added to the (inherited) `args`. `hide` and `aliases` are
+/// not inherited, so hidden tasks can serve as templates. `chain` holds the
+/// tasks extended by `task_name`, to detect cycles.
+fn extend_task(
+    tasks: &toml::Table,
+    task_name: &str,
+    chain: &mut Vec<String>,
+) -> std::result::Result<toml::Value, ExtendsError> {
+    let error = |message: String| ExtendsError {
+        message,
+        task_name: task_name.to_string(),
+    };
+    let task = &tasks[task_name];
+    let Some(table) = task.as_table() else {
+        return Ok(task.clone());
+    };
+
+    let mut resolved = match table.get("extends") {
+        None => toml::Table::new(),
+        Some(toml::Value::String(base)) => {
+            if base == task_name || chain.contains(base) {
+                let cycle = chain
+                    .iter()
+                    .map(String::as_str)
+                    .chain([task_name, base.as_str()])
+                    .collect::<Vec<_>>();
+                return Err(error(format!(
+                    "Task '{task_name}' can not extend '{base}', the tasks extend each other: {}",
+                    cycle.join(" → ")
+                )));
+            }
+            if !tasks.get(base).is_some_and(toml::Value::is_table) {
+                return Err(error(format!(
+                    "Task '{task_name}' extends '{base}', which is not a task."
+                )));
+            }
+            chain.push(task_name.to_string());
+            let base_task = extend_task(tasks, base, chain)?;
+            chain.pop();
+            let mut base_table = base_task.as_table().cloned().unwrap_or_default();
+            base_table.remove("hide");
+            base_table.remove("aliases");
+            base_table
+        }
+        Some(_) => {
+            return Err(error(format!(
+                "The `extends` of task '{task_name}' has to be the name of a task."
+            )));
+        }
+    };
+
+    for (key, value) in table {
+        match (key.as_str(), resolved.get_mut(key), value) {
+            ("extends" | "append_args", _, _) => {}
+            ("env", Some(toml::Value::Table(env)), toml::Value::Table(overrides)) => {
+                env.extend(overrides.clone())
+            }
+            _ => {
+                resolved.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    if let Some(append_args) = table.get("append_args") {
+        let args = resolved
+            .entry("args")
+            .or_insert(toml::Value::Array(Vec::new()));
+        match (args, append_args) {
+            (toml::Value::Array(args), toml::Value::Array(append_args)) => {
+                args.extend(append_args.iter().cloned())
+            }
+            _ => {
+                return Err(error(format!(
+                    "The `args` and `append_args` of task '{task_name}' have to be lists."
+                )));
+            }
+        }
+    }
+    Ok(toml::Value::Table(resolved))
+}
+
+/// Builds an error pointing at the task that made the config invalid
+///
+/// The task types are untagged, so serde only reports that a task did not
+/// match any of them. Here each task type is tried separately to explain why.
+fn diagnose_config(
+    config_table: &toml::Table,
+    sources: &[ConfigSource],
+    error: toml::de::Error,
+) -> ConfigError {
+    let invalid_task = config_table
+        .get("tasks")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flatten()
+        .map(|(task_name, value)| (task_name, AlchemistTaskType::mismatches(value), value))
+        .find(|(_, mismatches, _)| !mismatches.is_empty());
+
+    let Some((task_name, mismatches, value)) = invalid_task else {
+        return ConfigError {
+            message: format!("Invalid configuration: {}", error.message().trim()),
+            file: sources[0].file.clone(),
+            location: None,
+            notes: Vec::new(),
+        };
+    };
+
+    // The type the user most likely meant is the one whose main field is set.
+    let likely = mismatches.iter().find(|m| {
+        value
+            .as_table()
+            .is_some_and(|table| table.contains_key(m.key))
+    });
+    let message = match likely {
+        Some(mismatch) => format!(
+            "Task '{}' is not a valid {} task: {}{}",
+            task_name,
+            mismatch.task_type,
+            mismatch.reason,
+            did_you_mean(&mismatch.reason)
+                .map(|s| format!(", did you mean `{s}`?"))
+                .unwrap_or_default()
+        ),
+        None => format!("Task '{task_name}' does not match any task type"),
+    };
+    let notes = mismatches
+        .iter()
+        .map(|m| format!("tried {} task: {}", m.task_type, m.reason))
+        .collect();
+
+    // Point at the offending field when it is known, otherwise at the task.
+    let field = likely.and_then(|m| backticked(&m.reason).into_iter().next());
+    let (file, location) = sources
+        .iter()
+        .find_map(|source| {
+            task_span(source, task_name, field.as_deref()).map(|span| {
+                (
+                    source.file.clone(),
+                    Some(Box::new(SourceLocation::from_span(&source.content, span))),
+                )
+            })
+        })
+        .unwrap_or_else(|| (sources[0].file.clone(), None));
+
+    ConfigError {
+        message,
+        file,
+        location,
+        notes,
+    }
+}
+
+/// Finds the span of `field` of a task (or of the task name itself) in a config file
+fn task_span(
+    source: &ConfigSource,
+    task_name: &str,
+    field: Option<&str>,
+) -> Option<std::ops::Range<usize>> {
+    let document = toml_edit::ImDocument::parse(source.content.as_str()).ok()?;
+    let tasks = document.get("tasks")?.as_table_like()?;
+    let (task_key, task) = tasks.get_key_value(task_name)?;
+    field
+        .and_then(|field| task.as_table_like()?.get_key_value(field))
+        .and_then(|(key, _)| key.span())
+        .or_else(|| task_key.span())
+}
+
+/// Returns the words quoted in backticks in a serde error message
+fn backticked(message: &str) -> Vec<String> {
+    message
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Suggests the expected field closest to the unknown field in a serde error message
+fn did_you_mean(message: &str) -> Option<String> {
+    if !message.starts_with("unknown field") {
+        return None;
+    }
+    let words = backticked(message);
+    let (unknown, expected) = words.split_first()?;
+    expected
+        .iter()
+        .map(|candidate| (edit_distance(unknown, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Deep-merges `overrides` into `base`
+///
+/// Tables are merged key by key, any other value in `overrides` replaces the
+/// one in `base`.
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(override_table)) => {
+                merge_tables(base_table, override_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
 // allow because we might reuse the PathBuf in the future
 #[allow(clippy::ptr_arg)]
 pub fn set_cwd_to_config_dir(config_file_path: &PathBuf) -> Result<()> {
+    // A config from stdin has no directory, tasks run from the current one.
+    if is_stdin_config(config_file_path) {
+        return Ok(());
+    }
     let config_location = config_file_path
         .parent()
         .ok_or_else(|| AssertionError(String::from("No access to config parent directory")))?;
@@ -4,7 +4,7 @@ use crate::error::{AlchemistError, ErrorContext};
 
 #[test]
 fn locate_config_in_checked_out_repo() {
-    let config = locate_config().unwrap();
+    let config = locate_config(None).unwrap();
     let nr_of_slashes_in_path = config
         .to_string_lossy()
         .chars()
@@ -28,7 +28,7 @@ fn local_config_in_tempdir() {
     fs::create_dir_all(&cmd_dir).unwrap();
     set_current_dir(&cmd_dir).unwrap();
 
-    let config_not_found = locate_config().unwrap_err();
+    let config_not_found = locate_config(None).unwrap_err();
     assert_eq!(
         config_not_found,
         AlchemistError::AssertionErrorVariant(ErrorContext(
@@ -39,7 +39,7 @@ fn local_config_in_tempdir() {
 
     // create file like touch from config_path
     std::fs::File::create(&config_path).unwrap();
-    let config_found = locate_config().unwrap();
+    let config_found = locate_config(None).unwrap();
     assert_eq!(
         std::fs::canonicalize(config_found).unwrap(),
         std::fs::canonicalize(config_path).unwrap()
@@ -48,6 +48,36 @@ fn local_config_in_tempdir() {
     set_current_dir(original_cwd).unwrap();
 }
 
+#[test]
+fn locate_config_explicit_file() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("other.toml");
+
+    let config_not_found = locate_config(Some(config_path.clone())).unwrap_err();
+    assert_eq!(
+        config_not_found,
+        AlchemistError::AssertionErrorVariant(ErrorContext(
+            AssertionError(format!(
+                "'{}' does not exist or is not a file.",
+                config_path.display()
+            )),
+            None
+        ))
+    );
+
+    std::fs::File::create(&config_path).unwrap();
+    assert_eq!(
+        locate_config(Some(config_path.clone())).unwrap(),
+        config_path
+    );
+
+    // stdin is passed on without checking the filesystem.
+    assert_eq!(
+        locate_config(Some(PathBuf::from(STDIN_CONFIG))).unwrap(),
+        PathBuf::from("-")
+    );
+}
+
 #[test]
 fn parse_config_order_of_tasks() {
     let original_cwd = current_dir().unwrap(); // save original cwd
@@ -71,7 +101,7 @@ fn parse_config_order_of_tasks() {
 
     let config = parse_config(&config_path).unwrap();
     assert_eq!(config.tasks.len(), 12);
-    for (i, (task_name, task_enum)) in config.tasks.iter().enumerate() {
+    for (i, (task_name, _task_enum)) in config.tasks.iter().enumerate() {
         // After 12 tasks in-order, smells ok.
         assert_eq!(task_name, &format!("task{}", i + 1));
     }
@@ -79,6 +109,394 @@ fn parse_config_order_of_tasks() {
     set_current_dir(original_cwd).unwrap(); // restore original cwd
 }
 
+#[test]
+fn workspace_members_and_project_references() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let root_config = tempdir.path().join("alchemist.toml");
+    fs::write(&root_config, "workspace = [\"services/*\"]\n[tasks]\n").unwrap();
+
+    for member in ["api", "web"] {
+        let member_dir = tempdir.path().join("services").join(member);
+        fs::create_dir_all(&member_dir).unwrap();
+        fs::write(
+            member_dir.join("alchemist.toml"),
+            "[tasks.build]\ncommand = \"true\"\n",
+        )
+        .unwrap();
+    }
+    // Directories without a config file are not members.
+    fs::create_dir_all(tempdir.path().join("services").join("docs")).unwrap();
+
+    let config = parse_config(&root_config).unwrap();
+    let members = config.workspace_members().unwrap();
+    assert_eq!(
+        members,
+        vec![
+            tempdir.path().join("services").join("api"),
+            tempdir.path().join("services").join("web"),
+        ]
+    );
+
+    let (api_config, _task) = config.resolve_task("services/api:build").unwrap().unwrap();
+    assert_eq!(
+        api_config.project_dir,
+        Some(tempdir.path().join("services").join("api"))
+    );
+    assert!(config.resolve_task("services/api:test").unwrap().is_none());
+    assert!(
+        config
+            .resolve_task("services/missing:build")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn resolve_task_with_colon_in_name() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "[tasks.\"test:unit\"]\ncommand = \"true\"\naliases = [\"lint:fix\"]\n",
+    )
+    .unwrap();
+    let config = parse_config(&config_path).unwrap();
+    let (task_config, _task) = config.resolve_task("test:unit").unwrap().unwrap();
+    assert_eq!(task_config.project_dir, config.project_dir);
+    assert!(config.resolve_task("lint:fix").unwrap().is_some());
+    assert!(config.resolve_task("test:other").unwrap().is_none());
+}
+
+#[test]
+fn merge_global_config_project_takes_precedence() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let project_config = tempdir.path().join("alchemist.toml");
+    let global_config = tempdir.path().join("global.toml");
+    fs::write(&project_config, "[tasks.build]\ncommand = \"make\"\n").unwrap();
+    fs::write(
+        &global_config,
+        "[tasks.build]\ncommand = \"cargo\"\n\n[tasks.todo]\ncommand = \"vim\"\n",
+    )
+    .unwrap();
+
+    let mut config = parse_config(&project_config).unwrap();
+    config.merge_global_config(&global_config).unwrap();
+
+    assert_eq!(
+        config.tasks.keys().collect::<Vec<_>>(),
+        vec!["build", "todo"]
+    );
+    assert_eq!(config.tasks["build"].describe().description, vec!["make "]);
+    assert_eq!(config.global_tasks.iter().collect::<Vec<_>>(), vec!["todo"]);
+}
+
+#[test]
+fn parse_config_with_local_overrides() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        r#"
+        [tasks.migrate]
+        command = "migrate"
+        args = ["up"]
+        env = { DB_URL = "postgres://shared", LOG = "info" }
+
+        [tasks.lint]
+        command = "lint"
+        "#,
+    )
+    .unwrap();
+    fs::write(
+        tempdir.path().join("alchemist.local.toml"),
+        r#"
+        [tasks.migrate]
+        env = { DB_URL = "postgres://mine" }
+
+        [tasks.lint]
+        hide = true
+
+        [tasks.mine]
+        command = "true"
+        "#,
+    )
+    .unwrap();
+
+    let config = parse_config(&config_path).unwrap();
+    assert_eq!(
+        config.tasks.keys().collect::<Vec<_>>(),
+        vec!["migrate", "lint", "mine"]
+    );
+    match &config.tasks["migrate"] {
+        AlchemistTaskType::AlchemistBasicTask(task) => {
+            let env = task.env.as_ref().unwrap();
+            assert_eq!(env["DB_URL"], "postgres://mine");
+            assert_eq!(env["LOG"], "info");
+            assert_eq!(task.args, Some(vec!["up".to_string()]));
+        }
+        _ => panic!("migrate should be a basic task"),
+    }
+    assert!(!config.tasks["lint"].is_shown());
+}
+
+#[test]
+fn parse_config_resolves_extends() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        r#"
+        [tasks.base_cargo]
+        command = "cargo"
+        args = ["build"]
+        env = { RUSTFLAGS = "-Dwarnings", CARGO_TERM_COLOR = "always" }
+        hide = true
+
+        [tasks.release]
+        extends = "base_cargo"
+        append_args = ["--release"]
+        env = { CARGO_TERM_COLOR = "never" }
+
+        [tasks.test]
+        extends = "release"
+        args = ["test"]
+        "#,
+    )
+    .unwrap();
+
+    let config = parse_config(&config_path).unwrap();
+    match &config.tasks["release"] {
+        AlchemistTaskType::AlchemistBasicTask(task) => {
+            let env = task.env.as_ref().unwrap();
+            assert_eq!(env["RUSTFLAGS"], "-Dwarnings");
+            assert_eq!(env["CARGO_TERM_COLOR"], "never");
+            assert_eq!(
+                task.args,
+                Some(vec!["build".to_string(), "--release".to_string()])
+            );
+        }
+        _ => panic!("release should be a basic task"),
+    }
+    match &config.tasks["test"] {
+        AlchemistTaskType::AlchemistBasicTask(task) => {
+            assert_eq!(task.args, Some(vec!["test".to_string()]));
+            assert_eq!(task.env.as_ref().unwrap()["CARGO_TERM_COLOR"], "never");
+        }
+        _ => panic!("test should be a basic task"),
+    }
+    assert!(!config.tasks["base_cargo"].is_shown());
+    assert!(config.tasks["release"].is_shown());
+}
+
+#[test]
+fn parse_config_reports_extends_cycles() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "[tasks.a]\nextends = \"b\"\n\n[tasks.b]\nextends = \"a\"\ncommand = \"true\"\n",
+    )
+    .unwrap();
+
+    let err = config_error(parse_config(&config_path).unwrap_err());
+    assert_eq!(
+        err.message,
+        "Task 'b' can not extend 'a', the tasks extend each other: a → b → a"
+    );
+    assert_eq!(err.location.unwrap().line, 5);
+
+    fs::write(&config_path, "[tasks.a]\nextends = \"missing\"\n").unwrap();
+    let err = config_error(parse_config(&config_path).unwrap_err());
+    assert_eq!(
+        err.message,
+        "Task 'a' extends 'missing', which is not a task."
+    );
+}
+
+#[test]
+fn parse_config_resolves_aliases() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "[tasks.build]\ncommand = \"true\"\naliases = [\"b\", \"bld\"]\n",
+    )
+    .unwrap();
+
+    let config = parse_config(&config_path).unwrap();
+    config.check_tasks().unwrap();
+    assert_eq!(config.task_name("bld"), "build");
+    assert_eq!(config.task_name("build"), "build");
+    assert!(config.resolve_task("b").unwrap().is_some());
+    assert!(config.resolve_task("c").unwrap().is_none());
+}
+
+#[test]
+fn parse_config_reports_alias_collisions() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "[tasks.build]\ncommand = \"true\"\naliases = [\"b\"]\n\n[tasks.bench]\ncommand = \"true\"\naliases = [\"b\"]\n",
+    )
+    .unwrap();
+    let err = config_error(
+        parse_config(&config_path)
+            .unwrap()
+            .check_tasks()
+            .unwrap_err(),
+    );
+    assert_eq!(
+        err.message,
+        "The alias 'b' of task 'bench' is already an alias of task 'build'."
+    );
+    assert_eq!(err.location.unwrap().line, 7);
+
+    fs::write(
+        &config_path,
+        "[tasks.build]\ncommand = \"true\"\naliases = [\"test\"]\n\n[tasks.test]\ncommand = \"true\"\n",
+    )
+    .unwrap();
+    let err = config_error(
+        parse_config(&config_path)
+            .unwrap()
+            .check_tasks()
+            .unwrap_err(),
+    );
+    assert_eq!(
+        err.message,
+        "The alias 'test' of task 'build' is already the name of a task."
+    );
+
+    // Aliases of global tasks are checked against the tasks of the project
+    fs::write(&config_path, "[tasks.test]\ncommand = \"true\"\n").unwrap();
+    let global_config = tempdir.path().join("global.toml");
+    fs::write(
+        &global_config,
+        "[tasks.todo]\ncommand = \"vim\"\naliases = [\"test\"]\n",
+    )
+    .unwrap();
+    let mut config = parse_config(&config_path).unwrap();
+    config.merge_global_config(&global_config).unwrap();
+    let err = config_error(config.check_tasks().unwrap_err());
+    assert_eq!(
+        err.message,
+        "The alias 'test' of task 'todo' is already the name of a task."
+    );
+    assert!(err.file.ends_with("global.toml"));
+}
+
+#[test]
+fn parse_config_checks_default_task() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "default = \"d\"\n\n[tasks.dev]\ncommand = \"true\"\naliases = [\"d\"]\n",
+    )
+    .unwrap();
+    let config = parse_config(&config_path).unwrap();
+    config.check_tasks().unwrap();
+    assert_eq!(config.default.as_deref(), Some("d"));
+
+    fs::write(
+        &config_path,
+        "default = \"serve\"\n\n[tasks.dev]\ncommand = \"true\"\n",
+    )
+    .unwrap();
+    let mut config = parse_config(&config_path).unwrap();
+    let err = config_error(config.check_tasks().unwrap_err());
+    assert_eq!(err.message, "The default task 'serve' does not exist.");
+    assert_eq!(err.location.unwrap().line, 1);
+
+    // The default can be a global task
+    let global_config = tempdir.path().join("global.toml");
+    fs::write(&global_config, "[tasks.serve]\ncommand = \"true\"\n").unwrap();
+    config.merge_global_config(&global_config).unwrap();
+    config.check_tasks().unwrap();
+}
+
+#[test]
+fn parse_config_checks_matrices() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    for (matrix, message) in [
+        ("{}", "Matrix task 'build' has no axes."),
+        (
+            "{ target = [] }",
+            "The axis 'target' of matrix task 'build' has no values.",
+        ),
+        (
+            "{ target = [\"a,b\"] }",
+            "The value 'a,b' of axis 'target' of matrix task 'build' can not contain ',', '=', '[' or ']', or start or end with whitespace.",
+        ),
+    ] {
+        fs::write(
+            &config_path,
+            format!("[tasks.build]\ncommand = \"true\"\nmatrix = {matrix}\n"),
+        )
+        .unwrap();
+        let err = config_error(parse_config(&config_path).unwrap_err());
+        assert_eq!(err.message, message);
+        assert_eq!(err.location.unwrap().line, 3);
+    }
+}
+
+fn config_error(err: AlchemistError) -> crate::error::ConfigError {
+    match err {
+        AlchemistError::ConfigErrorVariant(ErrorContext(e, _)) => e,
+        other => panic!("expected a config error, got {other:?}"),
+    }
+}
+
+#[test]
+fn parse_config_reports_unknown_field_location() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(
+        &config_path,
+        "[tasks.ok]\ncommand = \"true\"\n\n[tasks.build]\ncommand = \"cargo\"\nagrs = [\"build\"]\n",
+    )
+    .unwrap();
+
+    let err = config_error(parse_config(&config_path).unwrap_err());
+    assert!(
+        err.message
+            .starts_with("Task 'build' is not a valid command task: unknown field `agrs`")
+    );
+    assert!(err.message.ends_with(", did you mean `args`?"));
+    assert_eq!(err.file, config_path.display().to_string());
+    let location = err.location.unwrap();
+    assert_eq!((location.line, location.column, location.width), (6, 1, 4));
+    assert_eq!(location.source_line, "agrs = [\"build\"]");
+    assert_eq!(err.notes.len(), 6);
+    assert!(err.notes[1].starts_with("tried serial task: unknown field `command`"));
+}
+
+#[test]
+fn parse_config_reports_syntax_error_location() {
+    let tempdir = tempfile::tempdir().unwrap();
+    let config_path = tempdir.path().join("alchemist.toml");
+    fs::write(&config_path, "[tasks.build]\ncommand = \"cargo\n").unwrap();
+
+    let err = config_error(parse_config(&config_path).unwrap_err());
+    assert_eq!(err.message, "Invalid configuration: invalid basic string");
+    let location = err.location.unwrap();
+    assert_eq!((location.line, location.column), (2, 17));
+}
+
+#[test]
+fn did_you_mean_suggestions() {
+    let message = "unknown field `agrs`, expected one of `command`, `args`, `env`, `hide`, `watch`";
+    assert_eq!(did_you_mean(message), Some("args".to_string()));
+    assert_eq!(
+        did_you_mean("unknown field `xyz`, expected `shell_script` or `hide`"),
+        None
+    );
+    assert_eq!(did_you_mean("missing field `command`"), None);
+    assert_eq!(edit_distance("serial_task", "serial_tasks"), 1);
+}
+
 // TODO:
 //  - more test for when config not found
 //  - more fns to test
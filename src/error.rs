@@ -40,35 +40,145 @@ impl<T> From<AssertionError> for Result<T> {
     }
 }
 
+/// A location inside a config file, used to show the offending line
+#[derive(Debug, PartialEq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+    pub width: usize,
+}
+
+impl SourceLocation {
+    /// Determines the line and column of the byte `span` within `content`
+    pub fn from_span(content: &str, span: std::ops::Range<usize>) -> Self {
+        let start = span.start.min(content.len());
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[start..]
+            .find('\n')
+            .map_or(content.len(), |i| start + i);
+        let source_line = content[line_start..line_end].trim_end_matches('\r');
+        let column = content[line_start..start].chars().count() + 1;
+        let width = content[start..span.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+        Self {
+            line: content[..start].matches('\n').count() + 1,
+            column,
+            source_line: source_line.to_string(),
+            width,
+        }
+    }
+}
+
+/// An invalid config file, pointing at the offending part of the file
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub message: String,
+    pub file: String,
+    pub location: Option<Box<SourceLocation>>,
+    pub notes: Vec<String>,
+}
+
+impl std::error::Error for ConfigError {}
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        let Some(location) = &self.location else {
+            write!(f, "\n  {} {}", "-->".blue(), self.file)?;
+            for note in &self.notes {
+                write!(f, "\n  {} {}", "=".blue(), note)?;
+            }
+            return Ok(());
+        };
+        let gutter = " ".repeat(location.line.to_string().len());
+        write!(
+            f,
+            "\n{}{} {}:{}:{}",
+            gutter,
+            "-->".blue(),
+            self.file,
+            location.line,
+            location.column
+        )?;
+        write!(f, "\n{} {}", gutter, "|".blue())?;
+        write!(
+            f,
+            "\n{} {} {}",
+            location.line.blue(),
+            "|".blue(),
+            location.source_line
+        )?;
+        write!(
+            f,
+            "\n{} {} {}{}",
+            gutter,
+            "|".blue(),
+            " ".repeat(location.column - 1),
+            "^".repeat(location.width).red().bold()
+        )?;
+        for note in &self.notes {
+            write!(f, "\n{} {} {}", gutter, "=".blue(), note)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> From<ConfigError> for Result<T> {
+    fn from(value: ConfigError) -> Self {
+        Err(value.into())
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum AlchemistError {
     IOErrorVariant(ErrorContext<std::io::Error>),
     AssertionErrorVariant(ErrorContext<AssertionError>),
     TomlParseErrorVariant(ErrorContext<toml::de::Error>),
+    ConfigErrorVariant(ErrorContext<ConfigError>),
+}
+
+impl AlchemistError {
+    /// The name of the kind of error, e.g. `IOError`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::IOErrorVariant(_) => "IOError",
+            Self::AssertionErrorVariant(_) => "AssertionError",
+            Self::TomlParseErrorVariant(_) => "TomlParseError",
+            Self::ConfigErrorVariant(_) => "ConfigError",
+        }
+    }
+
+    /// The error message without the kind of error
+    pub fn message(&self) -> String {
+        match self {
+            Self::IOErrorVariant(e) => e.to_string(),
+            Self::AssertionErrorVariant(e) => e.to_string(),
+            Self::TomlParseErrorVariant(e) => e.to_string(),
+            Self::ConfigErrorVariant(e) => e.to_string(),
+        }
+    }
 }
 
 impl std::fmt::Display for AlchemistError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (variant, e) = match self {
-            Self::IOErrorVariant(e) => ("IOError", e.to_string()),
-            Self::AssertionErrorVariant(e) => ("AssertionError", e.to_string()),
-            Self::TomlParseErrorVariant(e) => ("TomlParseError", e.to_string()),
-        };
         write!(
             f,
             "{}{}{}{}{}",
             crate::cli::terminal::error_prefix(),
             "[".dimmed(),
-            variant.dimmed().italic(),
+            self.kind().dimmed().italic(),
             "]: ".dimmed(),
-            e
+            self.message()
         )
     }
 }
 
 impl PartialEq for AlchemistError {
     fn eq(&self, other: &Self) -> bool {
-        return self.to_string() == other.to_string();
+        self.to_string() == other.to_string()
     }
 }
 
@@ -78,6 +188,12 @@ impl From<AssertionError> for AlchemistError {
     }
 }
 
+impl From<ConfigError> for AlchemistError {
+    fn from(value: ConfigError) -> Self {
+        Self::ConfigErrorVariant(ErrorContext(value, None))
+    }
+}
+
 impl From<std::io::Error> for AlchemistError {
     fn from(value: std::io::Error) -> Self {
         Self::IOErrorVariant(ErrorContext(value, None))
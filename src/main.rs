@@ -6,26 +6,62 @@ mod cli;
 
 mod config;
 mod error;
+mod process;
+mod report;
 mod tasks;
+mod watch;
 use std::env;
+use std::io::IsTerminal;
 
-use clap::Parser;
+use crate::error::ResultContext;
+
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
 use owo_colors::OwoColorize;
 
 use crate::cli::interface;
 use crate::cli::interface::CliArgs;
-use crate::cli::terminal;
+use crate::cli::terminal::{self, Event, OutputFormat, Settings};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
-    let args = CliArgs::parse();
+    CompleteEnv::with_factory(CliArgs::command).complete();
+    let mut args = CliArgs::parse();
+    terminal::configure(Settings {
+        format: args.output,
+        color: args.color,
+        verbosity: args.verbose,
+        silent: args.silent,
+    });
 
-    let config_file_path = crate::config::locate_config().ok();
+    if let Some(directory) = &args.directory
+        && let Err(e) = env::set_current_dir(directory).error_msg(format!(
+            "Can not change to directory '{}'.",
+            directory.display()
+        ))
+    {
+        terminal::error(e.into());
+        return;
+    }
 
-    if !args.quiet {
-        println!("{} version {}\n", "alchemist".green(), VERSION.yellow());
-        if let Some(config_file_path) = config_file_path {
+    // Before the banner, which would end up in a script written to stdout.
+    if let Some(shell) = args.shell_complete {
+        if let Err(e) = interface::generate_completions(shell, args.stdout) {
+            terminal::error(e);
+        }
+        return;
+    }
+
+    let config_file_path = crate::config::locate_config(args.file.clone()).ok();
+
+    if !args.quiet && !args.silent && args.output == OutputFormat::Text {
+        terminal::print(format!(
+            "{} version {}\n",
+            "alchemist".green(),
+            VERSION.yellow()
+        ));
+        if let Some(config_file_path) = &config_file_path {
             terminal::info(format!(
                 "Using alchemist file: {}\n",
                 config_file_path.display().yellow()
@@ -42,24 +78,86 @@ fn main() {
             Ok(_) => terminal::ok("Created template file!"),
             Err(e) => terminal::error(e),
         }
-        return ();
+        return;
     }
     if args.list {
-        if let Err(e) = interface::list_available_tasks(args.verbose) {
+        if let Err(e) = interface::list_available_tasks(&args) {
             terminal::error(e);
         }
         return;
     }
-    if args.shell_complete {
-        interface::generate_completions();
-        return ();
+    if args.commands.is_empty() && !args.interactive && config_file_path.is_some() {
+        match interface::default_task(&args) {
+            Ok(default) => args.commands.extend(default),
+            Err(e) => {
+                terminal::error(e);
+                return;
+            }
+        }
+    }
+    // Without a terminal to prompt in we fall back to the warning below.
+    let stdin_is_terminal = std::io::stdin().is_terminal();
+    if args.commands.is_empty()
+        && stdin_is_terminal
+        && (args.interactive || std::io::stdout().is_terminal())
+    {
+        match interface::pick_tasks(&args) {
+            Ok(picked) => args.commands = picked,
+            Err(e) => {
+                terminal::error(e);
+                return;
+            }
+        }
     }
-    if args.commands.len() == 0 {
+    if args.commands.is_empty() {
         terminal::warn("No commands were provided to run. run alchemist --help for more info.");
-        return ();
+        return;
+    }
+    if args.explain {
+        if let Err(e) = interface::explain_tasks(&args) {
+            terminal::error(e);
+        }
+        return;
+    }
+    // The log directory is relative to where alchemist was started, not to the config.
+    let log_dir = args
+        .log_dir
+        .as_ref()
+        .map(std::path::absolute)
+        .transpose()
+        .error_msg("Can not access the log directory.");
+    match log_dir {
+        Ok(log_dir) => process::set_log_options(log_dir, args.log_timestamps),
+        Err(e) => {
+            terminal::error(e.into());
+            return;
+        }
+    }
+    if args.report_junit.is_some() {
+        report::capture_output();
+    }
+    report::set_run_outputs(args.report_junit.clone(), args.timings);
+    if let Err(e) = process::handle_signals() {
+        terminal::error(e);
+        return;
+    }
+    let result = if args.watch {
+        interface::watch_tasks(&args)
+    } else if args.workspace {
+        interface::run_workspace_tasks(&args)
+    } else {
+        interface::run_tasks(&args)
+    };
+    if process::is_interrupted() {
+        process::exit_interrupted();
+    }
+    if !args.watch {
+        report::finish_run();
     }
-    match interface::run_tasks(args.commands) {
+    let success = result.is_ok();
+    match result {
         Ok(_) => terminal::ok("Finished running all given tasks."),
         Err(e) => terminal::error(e),
     }
+    terminal::event(Event::RunFinished { success });
 }
@@ -0,0 +1,578 @@
+//! Bookkeeping of the processes started by tasks
+//!
+//! Every child process of a task is registered here while it runs, so a
+//! whole invocation can be stopped from the outside (e.g. by watch mode or
+//! Ctrl-C). Each child runs in its own process group, so stopping it also
+//! stops every process it started.
+//!
+//! A child started while alchemist is the foreground of a terminal and no
+//! other child runs stays in the group of alchemist instead, so it can read
+//! from the terminal and Ctrl-C reaches alchemist as well.
+//!
+//! Cleanup work (the `finally` and `on_failure` tasks) is not cancelled by
+//! the first interruption, alchemist waits for it before exiting.
+
+#[cfg(test)]
+#[path = "process_test.rs"]
+mod process_test;
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once, OnceLock};
+use std::time::{Duration, Instant};
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::cli::terminal::{self, Event, OutputFormat, Stream};
+use crate::error::{Result, ResultContext};
+use crate::report;
+
+/// Time a service gets to stop after SIGTERM before it is killed
+const SERVICE_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time running tasks get to stop after a forwarded signal before they are killed
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct RunningProcess {
+    pid: u32,
+    /// Leads its own process group, see [`TrackedChild`]
+    own_group: bool,
+    task_name: String,
+    /// Started by cleanup work, see [`cleanup`]
+    cleanup: bool,
+}
+
+/// The signal that interrupted alchemist and the tasks that were running then
+struct Interruption {
+    signal: libc::c_int,
+    task_names: Vec<String>,
+}
+
+static RUNNING: Mutex<Vec<RunningProcess>> = Mutex::new(Vec::new());
+static SERVICES: Mutex<Vec<(String, TrackedChild)>> = Mutex::new(Vec::new());
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTION: Mutex<Option<Interruption>> = Mutex::new(None);
+static EXIT: Once = Once::new();
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+static LOG_TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+static PENDING_CLEANUPS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CLEANING_UP: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets where tasks without a `log_file` log to and whether log lines get timestamps
+pub fn set_log_options(dir: Option<PathBuf>, timestamps: bool) {
+    if let Some(dir) = dir {
+        let _ = LOG_DIR.set(dir);
+    }
+    LOG_TIMESTAMPS.store(timestamps, Ordering::SeqCst);
+}
+
+/// The directory every task logs its output to, if any
+pub fn log_dir() -> Option<&'static PathBuf> {
+    LOG_DIR.get()
+}
+
+/// A file the output of a task is written to, next to showing it
+pub struct TaskLog {
+    file: Mutex<File>,
+    timestamps: bool,
+    started: Instant,
+}
+
+impl TaskLog {
+    /// Creates (or truncates) the log file at `path`, with its directory
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(Self {
+            file: Mutex::new(File::create(path)?),
+            timestamps: LOG_TIMESTAMPS.load(Ordering::SeqCst),
+            started: Instant::now(),
+        })
+    }
+
+    /// Appends `line`, prefixed with the time since the task started when timestamps are on
+    fn write_line(&self, line: &str) {
+        let mut file = self.file.lock().expect("log file poisoned");
+        let _ = if self.timestamps {
+            writeln!(
+                file,
+                "[+{:.3}s] {line}",
+                self.started.elapsed().as_secs_f64()
+            )
+        } else {
+            writeln!(file, "{line}")
+        };
+    }
+}
+
+/// A child process that is registered as running until it is dropped
+///
+/// The child leads a new process group. Once it exits, processes it left
+/// behind in its group are killed, and dropping it without waiting kills
+/// the whole group.
+///
+/// Only a child sharing the terminal with alchemist stays in its group, it
+/// is signalled on its own.
+pub struct TrackedChild {
+    child: Child,
+    waited: bool,
+    own_group: bool,
+    output_threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl TrackedChild {
+    /// Spawns `cmd` for `task_name`, capturing its output when it is logged,
+    /// reported as JSON or kept for a report
+    pub fn spawn<S: ToString>(
+        cmd: &mut Command,
+        task_name: S,
+        log: Option<TaskLog>,
+    ) -> io::Result<Self> {
+        if Self::forwards_output(&log) {
+            return Self::spawn_with_output(cmd, task_name, log, |_, _| {});
+        }
+        Self::spawn_tracked(cmd, task_name.to_string())
+    }
+
+    /// Spawns `cmd` for `task_name` keeping the stdout the caller set up (a
+    /// file or a pipe to another process), its stderr is handled like in [`Self::spawn`]
+    pub fn spawn_keeping_stdout<S: ToString>(
+        cmd: &mut Command,
+        task_name: S,
+        log: Option<TaskLog>,
+    ) -> io::Result<Self> {
+        if Self::forwards_output(&log) {
+            return Self::spawn_forwarding(cmd, task_name.to_string(), log, |_, _| {}, false);
+        }
+        Self::spawn_tracked(cmd, task_name.to_string())
+    }
+
+    fn forwards_output(log: &Option<TaskLog>) -> bool {
+        log.is_some()
+            || terminal::output_format() == OutputFormat::Json
+            || report::is_capturing_output()
+    }
+
+    /// Spawns `cmd` for `task_name`, passing every line of its output (and
+    /// the stream it is on) to `on_line`
+    ///
+    /// The output is still shown (see [`terminal::output_line`]) and written
+    /// to `log`.
+    pub fn spawn_with_output<S: ToString, F: Fn(Stream, &str) + Send + Sync + 'static>(
+        cmd: &mut Command,
+        task_name: S,
+        log: Option<TaskLog>,
+        on_line: F,
+    ) -> io::Result<Self> {
+        Self::spawn_forwarding(cmd, task_name.to_string(), log, on_line, true)
+    }
+
+    fn spawn_forwarding<F: Fn(Stream, &str) + Send + Sync + 'static>(
+        cmd: &mut Command,
+        task_name: String,
+        log: Option<TaskLog>,
+        on_line: F,
+        forward_stdout: bool,
+    ) -> io::Result<Self> {
+        if forward_stdout {
+            cmd.stdout(Stdio::piped());
+        }
+        cmd.stderr(Stdio::piped());
+        let mut tracked = Self::spawn_tracked(cmd, task_name.clone())?;
+
+        let on_line = Arc::new(on_line);
+        let log = Arc::new(log);
+        let record = report::current();
+        let outputs: [(Stream, Option<Box<dyn Read + Send>>); 2] = [
+            (
+                Stream::Stdout,
+                tracked
+                    .stdout
+                    .take_if(|_| forward_stdout)
+                    .map(|o| Box::new(o) as Box<dyn Read + Send>),
+            ),
+            (
+                Stream::Stderr,
+                tracked
+                    .stderr
+                    .take()
+                    .map(|o| Box::new(o) as Box<dyn Read + Send>),
+            ),
+        ];
+        for (stream, output) in outputs {
+            let Some(output) = output else { continue };
+            let task_name = task_name.clone();
+            let on_line = on_line.clone();
+            let log = log.clone();
+            tracked.output_threads.push(std::thread::spawn(move || {
+                let mut output = BufReader::new(output);
+                let mut line = Vec::new();
+                while matches!(output.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(['\n', '\r']);
+                    terminal::output_line(&task_name, stream, text);
+                    report::add_output(record, stream, text);
+                    if let Some(log) = log.as_ref() {
+                        log.write_line(text);
+                    }
+                    on_line(stream, text);
+                    line.clear();
+                }
+            }));
+        }
+        Ok(tracked)
+    }
+
+    fn spawn_tracked(cmd: &mut Command, task_name: String) -> io::Result<Self> {
+        show_environment(cmd);
+        let mut running = RUNNING.lock().expect("process registry poisoned");
+        let own_group = !running.is_empty() || !is_foreground();
+        if own_group {
+            cmd.process_group(0);
+        }
+        let child = cmd.spawn()?;
+        running.push(RunningProcess {
+            pid: child.id(),
+            own_group,
+            task_name: task_name.clone(),
+            cleanup: is_cleaning_up(),
+        });
+        drop(running);
+        terminal::event(Event::CommandSpawned {
+            task: &task_name,
+            command: std::iter::once(cmd.get_program())
+                .chain(cmd.get_args())
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            pid: child.id(),
+        });
+        Ok(Self {
+            child,
+            waited: false,
+            own_group,
+            output_threads: Vec::new(),
+        })
+    }
+
+    /// Waits for the child to exit and for all of its output to be shown
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait();
+        self.waited = true;
+        if self.own_group {
+            signal_group(self.child.id(), libc::SIGKILL);
+        }
+        unregister(self.child.id());
+        for output_thread in self.output_threads.drain(..) {
+            let _ = output_thread.join();
+        }
+        status
+    }
+
+    /// Sends `signal` to the child and every process in its group
+    pub fn signal(&self, signal: libc::c_int) {
+        signal_process(self.child.id(), self.own_group, signal);
+    }
+
+    /// Kills the child and every process in its group
+    pub fn kill(&mut self) {
+        self.signal(libc::SIGKILL);
+    }
+}
+
+impl Deref for TrackedChild {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        &self.child
+    }
+}
+
+impl DerefMut for TrackedChild {
+    fn deref_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        if !self.waited {
+            self.kill();
+            let _ = self.wait();
+        }
+        unregister(self.child.id());
+    }
+}
+
+/// Whether stdin is a terminal that alchemist is the foreground of
+fn is_foreground() -> bool {
+    // SAFETY: isatty, tcgetpgrp and getpgrp have no memory safety requirements.
+    unsafe {
+        libc::isatty(libc::STDIN_FILENO) == 1
+            && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+    }
+}
+
+/// Sends `signal` to the group of `pid`, or only to `pid` when it shares the group of alchemist
+fn signal_process(pid: u32, own_group: bool, signal: libc::c_int) {
+    if own_group {
+        signal_group(pid, signal);
+    } else {
+        // SAFETY: kill(2) has no memory safety requirements.
+        unsafe {
+            libc::kill(pid as libc::pid_t, signal);
+        }
+    }
+}
+
+fn signal_group(pid: u32, signal: libc::c_int) {
+    // SAFETY: kill(2) has no memory safety requirements, at worst the
+    // group no longer exists and the call fails with ESRCH.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+/// Shows where and with which environment `cmd` runs, depending on the verbosity
+fn show_environment(cmd: &Command) {
+    for line in terminal::command_details(cmd, terminal::verbosity()) {
+        terminal::info(line);
+    }
+}
+
+fn unregister(pid: u32) {
+    RUNNING
+        .lock()
+        .expect("process registry poisoned")
+        .retain(|p| p.pid != pid);
+}
+
+/// Sends `signal` to every running process and the processes they started
+pub fn signal_all(signal: libc::c_int) {
+    for process in RUNNING.lock().expect("process registry poisoned").iter() {
+        signal_process(process.pid, process.own_group, signal);
+    }
+}
+
+/// Sends `signal` to every running process that is not part of cleanup work
+fn signal_tasks(signal: libc::c_int) {
+    for process in RUNNING.lock().expect("process registry poisoned").iter() {
+        if !process.cleanup {
+            signal_process(process.pid, process.own_group, signal);
+        }
+    }
+}
+
+fn is_running() -> bool {
+    !RUNNING
+        .lock()
+        .expect("process registry poisoned")
+        .is_empty()
+}
+
+fn is_running_tasks() -> bool {
+    RUNNING
+        .lock()
+        .expect("process registry poisoned")
+        .iter()
+        .any(|p| !p.cleanup)
+}
+
+/// Names of the tasks with a running process, each name once
+fn running_task_names() -> Vec<String> {
+    let mut task_names = Vec::<String>::new();
+    for process in RUNNING.lock().expect("process registry poisoned").iter() {
+        if !task_names.contains(&process.task_name) {
+            task_names.push(process.task_name.clone());
+        }
+    }
+    task_names
+}
+
+/// Stops all running processes and prevents tasks from starting new ones,
+/// except for cleanup work
+pub fn cancel(signal: libc::c_int) {
+    CANCELLED.store(true, Ordering::SeqCst);
+    signal_tasks(signal);
+}
+
+/// Whether tasks on this thread should stop, never during cleanup work
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst) && !is_cleaning_up()
+}
+
+/// Whether this thread is running cleanup work, see [`cleanup`]
+pub fn is_cleaning_up() -> bool {
+    CLEANING_UP.get()
+}
+
+/// Makes tasks on this thread cleanup work (or not)
+///
+/// Threads do not inherit this, threads running subtasks have to set it.
+pub fn set_cleaning_up(cleaning_up: bool) {
+    CLEANING_UP.set(cleaning_up);
+}
+
+/// Runs `f` as cleanup work, which an interruption does not cancel
+pub fn cleanup<T>(f: impl FnOnce() -> T) -> T {
+    let previous = is_cleaning_up();
+    set_cleaning_up(true);
+    let result = f();
+    set_cleaning_up(previous);
+    result
+}
+
+/// Announces cleanup work that is still to come, until it is dropped
+///
+/// When interrupted, alchemist waits for announced cleanup work before exiting.
+pub struct PendingCleanup(());
+
+impl PendingCleanup {
+    pub fn new() -> Self {
+        PENDING_CLEANUPS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for PendingCleanup {
+    fn drop(&mut self) {
+        PENDING_CLEANUPS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn has_pending_cleanup() -> bool {
+    PENDING_CLEANUPS.load(Ordering::SeqCst) > 0
+}
+
+/// Allows tasks to start again after a [`cancel`]
+pub fn reset_cancelled() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+/// Keeps a service running in the background until [`stop_services`]
+pub fn keep_service<S: ToString>(task_name: S, child: TrackedChild) {
+    SERVICES
+        .lock()
+        .expect("service registry poisoned")
+        .push((task_name.to_string(), child));
+}
+
+/// Stops all services, killing those that do not stop in time
+pub fn stop_services() {
+    let services = std::mem::take(&mut *SERVICES.lock().expect("service registry poisoned"));
+    for (task_name, mut child) in services {
+        child.signal(libc::SIGTERM);
+        let stopping = Instant::now();
+        while matches!(child.try_wait(), Ok(None)) && stopping.elapsed() < SERVICE_STOP_TIMEOUT {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        if matches!(child.try_wait(), Ok(None)) {
+            terminal::warn(format!(
+                "Service {task_name} did not stop in time, killing it."
+            ));
+            child.kill();
+        }
+        let _ = child.wait();
+        terminal::info(format!("Stopped service {task_name}"));
+    }
+}
+
+fn signal_name(signal: libc::c_int) -> &'static str {
+    match signal {
+        SIGINT => "SIGINT",
+        SIGTERM => "SIGTERM",
+        _ => "signal",
+    }
+}
+
+/// Handles SIGINT and SIGTERM by stopping all tasks in an orderly way
+///
+/// The signal is forwarded to every running process and no new processes
+/// are started. Processes that are still running after [`SHUTDOWN_TIMEOUT`]
+/// are killed. Cleanup work keeps running until it is done, or until the
+/// signal is received a second time, which kills everything. Then alchemist
+/// exits with `128 + signal`.
+pub fn handle_signals() -> Result<()> {
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM]).error_msg("Can not install signal handlers.")?;
+    std::thread::spawn(move || {
+        let Some(signal) = signals.forever().next() else {
+            return;
+        };
+        *INTERRUPTION.lock().expect("interruption poisoned") = Some(Interruption {
+            signal,
+            task_names: running_task_names(),
+        });
+        if is_running() {
+            terminal::warn(format!(
+                "Received {}, stopping running tasks (press Ctrl-C again to kill them)...",
+                signal_name(signal)
+            ));
+        }
+        cancel(signal);
+
+        let stopping = Instant::now();
+        let mut killed_tasks = false;
+        while is_running() || has_pending_cleanup() {
+            if signals.pending().next().is_some() {
+                break;
+            }
+            if !killed_tasks && stopping.elapsed() >= SHUTDOWN_TIMEOUT && is_running_tasks() {
+                terminal::warn("Killing tasks that did not stop.");
+                signal_tasks(libc::SIGKILL);
+                killed_tasks = true;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        if is_running() {
+            terminal::warn("Killing tasks that did not stop.");
+            signal_all(libc::SIGKILL);
+        }
+        exit_interrupted();
+    });
+    Ok(())
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTION
+        .lock()
+        .expect("interruption poisoned")
+        .is_some()
+}
+
+/// Reports the interrupted tasks and exits, only the first call has any effect
+///
+/// The summary and JUnit report of the run are written first, in watch mode
+/// only while a run is going on.
+pub fn exit_interrupted() {
+    EXIT.call_once(|| {
+        if !report::is_empty() {
+            report::finish_run();
+        }
+        let (signal, task_names) = match &*INTERRUPTION.lock().expect("interruption poisoned") {
+            Some(interruption) => (interruption.signal, interruption.task_names.clone()),
+            None => (SIGINT, Vec::new()),
+        };
+        if task_names.is_empty() {
+            terminal::warn(format!("Interrupted by {}.", signal_name(signal)));
+        } else {
+            terminal::warn(format!(
+                "Interrupted by {}, stopped tasks: {}",
+                signal_name(signal),
+                task_names.join(", ")
+            ));
+        }
+        std::process::exit(128 + signal);
+    });
+}
@@ -0,0 +1,207 @@
+use super::*;
+
+#[test]
+fn running_tasks_are_tracked_until_waited() {
+    let mut first = TrackedChild::spawn(Command::new("sleep").arg("0.2"), "tracked", None).unwrap();
+    let mut second =
+        TrackedChild::spawn(Command::new("sleep").arg("0.2"), "tracked", None).unwrap();
+
+    let task_names = running_task_names();
+    assert_eq!(task_names.iter().filter(|t| *t == "tracked").count(), 1);
+
+    first.wait().unwrap();
+    assert!(running_task_names().contains(&"tracked".to_string()));
+    second.wait().unwrap();
+    assert!(!running_task_names().contains(&"tracked".to_string()));
+}
+
+/// Whether `pid` is a process that has not exited yet
+fn is_alive(pid: &str) -> bool {
+    std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| !stat.contains(") Z "))
+}
+
+#[test]
+fn processes_left_behind_are_killed() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let pid_file = tmpdir.path().join("pid");
+    let mut child = TrackedChild::spawn(
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}", pid_file.display())),
+        "leaves_grandchild",
+        None,
+    )
+    .unwrap();
+    child.wait().unwrap();
+
+    let grandchild = std::fs::read_to_string(pid_file).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!is_alive(grandchild.trim()));
+}
+
+#[test]
+fn output_lines_are_passed_on() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let collected = lines.clone();
+    let mut child = TrackedChild::spawn_with_output(
+        Command::new("sh")
+            .arg("-c")
+            .arg("echo one; echo two >&2; printf three"),
+        "output",
+        None,
+        move |_, line| collected.lock().unwrap().push(line.to_string()),
+    )
+    .unwrap();
+    assert!(child.wait().unwrap().success());
+
+    let mut lines = lines.lock().unwrap().clone();
+    lines.sort();
+    assert_eq!(lines, vec!["one", "three", "two"]);
+}
+
+/// Runs the ignored test `test` of this module in a new session with a pty
+/// as its terminal, writing `input` to the pty once everything in `ready` was output
+///
+/// Returns the exit status of the test, `None` when it did not finish in
+/// time, and its output.
+fn run_in_pty(test: &str, ready: &[&str], input: &[u8]) -> (Option<ExitStatus>, String) {
+    // SAFETY: the pty calls have no memory safety requirements, `name` is
+    // a valid buffer of the given length.
+    let (master, slave) = unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0);
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let mut name = [0 as libc::c_char; 128];
+        assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+        let name = std::ffi::CStr::from_ptr(name.as_ptr());
+        let slave = File::options()
+            .read(true)
+            .write(true)
+            .open(name.to_str().unwrap())
+            .unwrap();
+        (<File as std::os::fd::FromRawFd>::from_raw_fd(master), slave)
+    };
+
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args([
+        "--exact",
+        &format!("process::process_test::{test}"),
+        "--ignored",
+        "--nocapture",
+        "--test-threads=1",
+    ])
+    .env("ALCHEMIST_PTY_TEST", "1")
+    .stdin(slave.try_clone().unwrap())
+    .stdout(slave.try_clone().unwrap())
+    .stderr(slave);
+    // SAFETY: setsid and ioctl are async-signal-safe, stdin is the pty.
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setsid();
+            if libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut test = cmd.spawn().unwrap();
+    drop(cmd);
+
+    let mut terminal = master.try_clone().unwrap();
+    let output = Arc::new(Mutex::new(String::new()));
+    let collected = output.clone();
+    std::thread::spawn(move || {
+        let mut master = master;
+        let mut buffer = [0; 1024];
+        while let Ok(n) = master.read(&mut buffer)
+            && n > 0
+        {
+            collected
+                .lock()
+                .unwrap()
+                .push_str(&String::from_utf8_lossy(&buffer[..n]));
+        }
+    });
+
+    let started = Instant::now();
+    let mut written = false;
+    while test.try_wait().unwrap().is_none() && started.elapsed() < Duration::from_secs(10) {
+        if !written && ready.iter().all(|r| output.lock().unwrap().contains(r)) {
+            terminal.write_all(input).unwrap();
+            written = true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let status = test.try_wait().unwrap();
+    if status.is_none() {
+        let _ = test.kill();
+        let _ = test.wait();
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let output = output.lock().unwrap().clone();
+    (status, output)
+}
+
+/// Run by [`tasks_can_read_from_the_terminal`] with a pty as its terminal
+#[test]
+#[ignore]
+fn reads_from_the_terminal() {
+    if std::env::var_os("ALCHEMIST_PTY_TEST").is_none() {
+        return;
+    }
+    let mut child = TrackedChild::spawn(
+        Command::new("sh")
+            .arg("-c")
+            .arg("echo reading:; read x; echo got:$x"),
+        "reads_terminal",
+        None,
+    )
+    .unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn tasks_can_read_from_the_terminal() {
+    let (status, output) = run_in_pty("reads_from_the_terminal", &["reading:"], b"hello\n");
+    assert!(status.is_some_and(|s| s.success()), "output: {output}");
+    assert!(output.contains("got:hello"), "output: {output}");
+}
+
+/// Run by [`ctrl_c_stops_tasks_handling_sigint`] with a pty as its terminal
+#[test]
+#[ignore]
+fn runs_a_task_handling_sigint() {
+    if std::env::var_os("ALCHEMIST_PTY_TEST").is_none() {
+        return;
+    }
+    let config = toml::from_str::<crate::config::AlchemistConfig>(
+        r#"
+        [tasks.both]
+        parallel_tasks = ["graceful", "slow"]
+
+        [tasks.graceful]
+        shell_script = "trap 'exit 0' INT; echo graceful:ready; while :; do sleep 0.1; done"
+
+        [tasks.slow]
+        shell_script = "echo slow:ready; sleep 30"
+        "#,
+    )
+    .unwrap();
+    handle_signals().unwrap();
+    let _ = crate::tasks::RunnableTask::run(&config.tasks["both"], "both", &config);
+    if is_interrupted() {
+        exit_interrupted();
+    }
+}
+
+#[test]
+fn ctrl_c_stops_tasks_handling_sigint() {
+    let (status, output) = run_in_pty(
+        "runs_a_task_handling_sigint",
+        &["graceful:ready", "slow:ready"],
+        b"\x03",
+    );
+    assert_eq!(status.and_then(|s| s.code()), Some(130), "output: {output}");
+    assert!(output.contains("Interrupted by SIGINT"), "output: {output}");
+}
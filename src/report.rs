@@ -0,0 +1,442 @@
+//! Records every task that runs, for the summary shown at the end of a run
+//!
+//! Tasks are recorded as a tree: a task started while another task runs on
+//! the same thread (or on a thread spawned by it, see [`set_current`]) is
+//! recorded as its subtask.
+
+#[cfg(test)]
+#[path = "report_test.rs"]
+mod report_test;
+
+mod junit;
+
+use std::cell::Cell;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use crate::cli::terminal::{self, Event, OutputFormat, Stream};
+use crate::error::{Result, ResultContext};
+use crate::process;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    Ok,
+    Failed,
+    /// Failed, but the task has `allow_failure` set
+    AllowedFailure,
+    Skipped,
+    Cancelled,
+}
+
+impl TaskStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            TaskStatus::Running => "running",
+            TaskStatus::Ok => "ok",
+            TaskStatus::Failed => "failed",
+            TaskStatus::AllowedFailure => "failed (allowed)",
+            TaskStatus::Skipped => "skipped",
+            TaskStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskRecord {
+    pub name: String,
+    pub task_type: String,
+    pub parent: Option<usize>,
+    pub status: TaskStatus,
+    pub started: Instant,
+    pub duration: Duration,
+    pub exit_status: Option<ExitStatus>,
+    /// Why the task failed, without colors
+    pub error: Option<String>,
+    /// The output of the process of the task, only kept when [`capture_output`] was called
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl TaskRecord {
+    fn exit_code(&self) -> Option<String> {
+        let status = self.exit_status?;
+        match (status.code(), status.signal()) {
+            (Some(code), _) => Some(code.to_string()),
+            (None, Some(signal)) => Some(format!("signal {signal}")),
+            (None, None) => None,
+        }
+    }
+
+    fn finished_event(&self, id: usize) -> Event<'_> {
+        Event::TaskFinished {
+            id,
+            parent: self.parent,
+            task: &self.name,
+            task_type: &self.task_type,
+            status: self.status,
+            duration_ms: self.duration.as_millis(),
+            exit_code: self.exit_status.and_then(|s| s.code()),
+            signal: self.exit_status.and_then(|s| s.signal()),
+        }
+    }
+}
+
+static RECORDS: Mutex<Vec<TaskRecord>> = Mutex::new(Vec::new());
+static CAPTURE_OUTPUT: AtomicBool = AtomicBool::new(false);
+/// Where [`finish_run`] writes the JUnit report and whether the summary shows timings
+static RUN_OUTPUTS: Mutex<(Option<PathBuf>, bool)> = Mutex::new((None, false));
+
+thread_local! {
+    static CURRENT: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+fn records() -> std::sync::MutexGuard<'static, Vec<TaskRecord>> {
+    RECORDS.lock().expect("task records poisoned")
+}
+
+/// The task running on this thread, subtasks started now belong to it
+pub fn current() -> Option<usize> {
+    CURRENT.get()
+}
+
+/// Makes tasks started on this thread subtasks of `task`
+///
+/// Needed on threads that run subtasks of a task started on another thread.
+pub fn set_current(task: Option<usize>) {
+    CURRENT.set(task);
+}
+
+fn push(task_name: &str, task_type: &str, status: TaskStatus) -> usize {
+    let mut records = records();
+    records.push(TaskRecord {
+        name: task_name.to_string(),
+        task_type: task_type.to_string(),
+        parent: current(),
+        status,
+        started: Instant::now(),
+        duration: Duration::ZERO,
+        exit_status: None,
+        error: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    });
+    records.len() - 1
+}
+
+/// Records that `task_name` started running, until [`finish`] is called
+pub fn start(task_name: &str, task_type: &str) -> usize {
+    let id = push(task_name, task_type, TaskStatus::Running);
+    terminal::event(Event::TaskStarted {
+        id,
+        parent: current(),
+        task: task_name,
+        task_type,
+    });
+    set_current(Some(id));
+    id
+}
+
+/// Records the outcome of the task started as `id`
+///
+/// Returns the status of the task, a failure is [`TaskStatus::AllowedFailure`]
+/// when `allow_failure` is set and the task was not cancelled.
+pub fn finish(id: usize, result: &Result<()>, allow_failure: bool) -> TaskStatus {
+    let mut records = records();
+    let record = &mut records[id];
+    record.duration = record.started.elapsed();
+    record.status = match result {
+        Ok(_) => TaskStatus::Ok,
+        Err(_) if process::is_cancelled() => TaskStatus::Cancelled,
+        Err(_) if allow_failure => TaskStatus::AllowedFailure,
+        Err(_) => TaskStatus::Failed,
+    };
+    record.error = result
+        .as_ref()
+        .err()
+        .map(|e| terminal::strip_ansi(&e.message()));
+    set_current(record.parent);
+    terminal::event(record.finished_event(id));
+    record.status
+}
+
+/// Records that `task_name` did not run, as a subtask of the current task
+pub fn skip(task_name: &str, task_type: &str) {
+    let id = push(task_name, task_type, TaskStatus::Skipped);
+    terminal::event(records()[id].finished_event(id));
+}
+
+/// Records how the process of the current task exited
+pub fn exit_status(status: &ExitStatus) {
+    let Some(id) = current() else {
+        return;
+    };
+    records()[id].exit_status = Some(*status);
+}
+
+/// Keeps the output of task processes, see [`add_output`]
+pub fn capture_output() {
+    CAPTURE_OUTPUT.store(true, Ordering::SeqCst);
+}
+
+pub fn is_capturing_output() -> bool {
+    CAPTURE_OUTPUT.load(Ordering::SeqCst)
+}
+
+/// Records a line of output of the process of `task`
+pub fn add_output(task: Option<usize>, stream: Stream, line: &str) {
+    let Some(id) = task else {
+        return;
+    };
+    if !is_capturing_output() {
+        return;
+    }
+    let mut records = records();
+    let output = match stream {
+        Stream::Stdout => &mut records[id].stdout,
+        Stream::Stderr => &mut records[id].stderr,
+    };
+    output.push_str(line);
+    output.push('\n');
+}
+
+/// Writes the recorded tasks as a JUnit XML report to `path`
+pub fn write_junit(path: &Path) -> Result<()> {
+    let xml = junit::junit_xml(&records());
+    std::fs::write(path, xml).error_msg(format!(
+        "Can not write the JUnit report to '{}'.",
+        path.display()
+    ))?;
+    Ok(())
+}
+
+/// Sets where [`finish_run`] writes the JUnit report and whether the summary shows timings
+pub fn set_run_outputs(junit: Option<PathBuf>, timings: bool) {
+    *RUN_OUTPUTS.lock().expect("run outputs poisoned") = (junit, timings);
+}
+
+/// Whether no task was recorded (yet)
+pub fn is_empty() -> bool {
+    records().is_empty()
+}
+
+/// Prints the summary and writes the JUnit report of the run
+///
+/// Tasks that are still running, because alchemist was interrupted, are
+/// recorded as cancelled.
+pub fn finish_run() {
+    cancel_running(&mut records());
+    let (junit, timings) = RUN_OUTPUTS.lock().expect("run outputs poisoned").clone();
+    print_summary(timings);
+    if let Some(path) = junit
+        && let Err(e) = write_junit(&path)
+    {
+        terminal::error(e);
+    }
+}
+
+fn cancel_running(records: &mut [TaskRecord]) {
+    for record in records
+        .iter_mut()
+        .filter(|r| r.status == TaskStatus::Running)
+    {
+        record.duration = record.started.elapsed();
+        record.status = TaskStatus::Cancelled;
+    }
+}
+
+/// Forgets all recorded tasks, e.g. before the next run in watch mode
+pub fn reset() {
+    records().clear();
+    set_current(None);
+}
+
+fn children(records: &[TaskRecord], parent: Option<usize>) -> Vec<usize> {
+    (0..records.len())
+        .filter(|&i| records[i].parent == parent)
+        .collect()
+}
+
+fn critical_path_from(records: &[TaskRecord], id: usize, path: &mut Vec<usize>) {
+    path.push(id);
+    let ran = children(records, Some(id))
+        .into_iter()
+        .filter(|&i| records[i].status != TaskStatus::Skipped)
+        .collect::<Vec<_>>();
+    if records[id].task_type == "parallel" {
+        if let Some(&slowest) = ran.iter().max_by_key(|&&i| records[i].duration) {
+            critical_path_from(records, slowest, path);
+        }
+    } else {
+        for child in ran {
+            critical_path_from(records, child, path);
+        }
+    }
+}
+
+/// The tasks that determined the total run time
+///
+/// Tasks run one after the other all count, of the subtasks of a parallel
+/// task only the slowest one does.
+pub fn critical_path(records: &[TaskRecord]) -> Vec<usize> {
+    let mut path = Vec::new();
+    for root in children(records, None) {
+        if records[root].status != TaskStatus::Skipped {
+            critical_path_from(records, root, &mut path);
+        }
+    }
+    path
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else if duration < Duration::from_secs(60) {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!(
+            "{}m{:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        )
+    }
+}
+
+fn summary_rows(
+    records: &[TaskRecord],
+    parent: Option<usize>,
+    depth: usize,
+    rows: &mut Vec<(usize, usize)>,
+) {
+    for id in children(records, parent) {
+        rows.push((id, depth));
+        summary_rows(records, Some(id), depth + 1, rows);
+    }
+}
+
+/// The summary table of all `records`, highlighting the critical path when `timings` is set
+pub fn summary(records: &[TaskRecord], timings: bool) -> String {
+    let mut rows = Vec::new();
+    summary_rows(records, None, 0, &mut rows);
+    let on_critical_path = if timings {
+        critical_path(records)
+    } else {
+        Vec::new()
+    };
+
+    let name_width = rows
+        .iter()
+        .map(|&(id, depth)| depth * 2 + records[id].name.chars().count())
+        .chain([4])
+        .max()
+        .unwrap_or_default();
+    let type_width = rows
+        .iter()
+        .map(|&(id, _)| records[id].task_type.chars().count())
+        .chain([4])
+        .max()
+        .unwrap_or_default();
+
+    let status_width = rows
+        .iter()
+        .map(|&(id, _)| records[id].status.label().len())
+        .chain([6])
+        .max()
+        .unwrap_or_default();
+
+    let mut summary = format!(
+        "{}\n",
+        format!(
+            "  {:<name_width$}  {:<type_width$}  {:<status_width$}  {:>8}  Exit",
+            "Task", "Type", "Status", "Time"
+        )
+        .bold()
+    );
+    for (id, depth) in rows {
+        let record = &records[id];
+        let marker = if on_critical_path.contains(&id) {
+            "▶".yellow().to_string()
+        } else {
+            " ".to_string()
+        };
+        let name = format!(
+            "{:<name_width$}",
+            format!("{}{}", "  ".repeat(depth), record.name)
+        );
+        let label = record.status.label();
+        // Padded outside of the colors, so empty columns can be trimmed.
+        let status = format!(
+            "{}{}",
+            match record.status {
+                TaskStatus::Ok => label.green().to_string(),
+                TaskStatus::Failed => label.red().to_string(),
+                TaskStatus::AllowedFailure => label.magenta().to_string(),
+                TaskStatus::Running | TaskStatus::Skipped => label.dimmed().to_string(),
+                TaskStatus::Cancelled => label.yellow().to_string(),
+            },
+            " ".repeat(status_width - label.len())
+        );
+        let time = match record.status {
+            TaskStatus::Skipped => String::new(),
+            _ => format_duration(record.duration),
+        };
+        let row = format!(
+            "{marker} {name}  {:<type_width$}  {status}  {:>8}  {}",
+            record.task_type.dimmed(),
+            time,
+            record.exit_code().unwrap_or_default()
+        );
+        summary.push_str(row.trim_end());
+        summary.push('\n');
+    }
+
+    if timings {
+        let leaves = on_critical_path
+            .iter()
+            .filter(|&&id| {
+                !on_critical_path
+                    .iter()
+                    .any(|&i| records[i].parent == Some(id))
+            })
+            .map(|&id| {
+                format!(
+                    "{} ({})",
+                    records[id].name,
+                    format_duration(records[id].duration)
+                )
+            })
+            .collect::<Vec<_>>();
+        let total = children(records, None)
+            .into_iter()
+            .map(|id| records[id].duration)
+            .sum::<Duration>();
+        summary.push_str(&format!(
+            "\n{} {} {}\n",
+            "▶ Critical path".yellow().bold(),
+            format!("({} total):", format_duration(total)).dimmed(),
+            leaves.join(" → ")
+        ));
+    }
+    summary
+}
+
+/// Prints the summary of the recorded tasks, if more than one task ran
+///
+/// With `timings` the summary is always printed and highlights the critical path.
+pub fn print_summary(timings: bool) {
+    if terminal::output_format() != OutputFormat::Text || terminal::is_silent() {
+        return;
+    }
+    let records = records();
+    if records.len() > 1 || (timings && !records.is_empty()) {
+        terminal::print(format!("\n{}", summary(&records, timings)));
+    }
+}
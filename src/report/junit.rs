@@ -0,0 +1,196 @@
+//! Writes recorded tasks as a JUnit XML report
+//!
+//! Serial and parallel tasks become test suites holding their subtasks as
+//! test cases. Nested suites are flattened and named after their path, e.g.
+//! `ci/checks`. Subtasks of other tasks (matrix instances, `finally` and
+//! `on_failure` tasks) are test cases of the nearest suite above them. Tasks
+//! that are not part of a serial or parallel task end up in a suite named
+//! `alchemist`.
+
+#[cfg(test)]
+#[path = "junit_test.rs"]
+mod junit_test;
+
+use std::time::Duration;
+
+use crate::cli::terminal;
+
+use super::{TaskRecord, TaskStatus};
+
+const ROOT_SUITE: &str = "alchemist";
+
+/// Whether `record` is a suite, serial and parallel tasks that did not run are test cases
+fn is_suite(record: &TaskRecord) -> bool {
+    matches!(record.task_type.as_str(), "serial" | "parallel")
+        && record.status != TaskStatus::Skipped
+}
+
+/// Escapes `text` for XML, dropping color codes and characters XML can not hold
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in terminal::strip_ansi(text).chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// The nearest suite above the record `id`
+fn suite_of(records: &[TaskRecord], id: usize) -> Option<usize> {
+    let mut parent = records[id].parent;
+    while let Some(id) = parent {
+        if is_suite(&records[id]) {
+            return Some(id);
+        }
+        parent = records[id].parent;
+    }
+    None
+}
+
+/// The test cases of the suite `suite`, or of the root suite
+fn cases_of(records: &[TaskRecord], suite: Option<usize>) -> Vec<&TaskRecord> {
+    (0..records.len())
+        .filter(|&id| !is_suite(&records[id]) && suite_of(records, id) == suite)
+        .map(|id| &records[id])
+        .collect()
+}
+
+fn suite_name(records: &[TaskRecord], id: usize) -> String {
+    match records[id].parent {
+        Some(parent) => format!("{}/{}", suite_name(records, parent), records[id].name),
+        None => records[id].name.clone(),
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    tests: usize,
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn add(&mut self, record: &TaskRecord) {
+        self.tests += 1;
+        match record.status {
+            TaskStatus::Failed => self.failures += 1,
+            TaskStatus::Cancelled | TaskStatus::Running => self.errors += 1,
+            TaskStatus::Skipped => self.skipped += 1,
+            TaskStatus::Ok | TaskStatus::AllowedFailure => {}
+        }
+    }
+
+    fn attributes(&self) -> String {
+        format!(
+            r#"tests="{}" failures="{}" errors="{}" skipped="{}""#,
+            self.tests, self.failures, self.errors, self.skipped
+        )
+    }
+}
+
+fn test_case(record: &TaskRecord, class_name: &str) -> String {
+    let mut case = format!(
+        r#"    <testcase name="{}" classname="{}" time="{}">"#,
+        escape(&record.name),
+        escape(class_name),
+        seconds(record.duration)
+    );
+    case.push('\n');
+    let message = record.error.as_deref().unwrap_or_default();
+    match record.status {
+        TaskStatus::Failed => case.push_str(&format!(
+            "      <failure message=\"{}\">{}</failure>\n",
+            escape(message.lines().next().unwrap_or_default()),
+            escape(message)
+        )),
+        TaskStatus::Cancelled => case.push_str(&format!(
+            "      <error message=\"cancelled\">{}</error>\n",
+            escape(message)
+        )),
+        TaskStatus::Running => case.push_str("      <error message=\"did not finish\"></error>\n"),
+        TaskStatus::Skipped => case.push_str("      <skipped/>\n"),
+        TaskStatus::AllowedFailure => {}
+        TaskStatus::Ok => {}
+    }
+    let mut stderr = record.stderr.clone();
+    if record.status == TaskStatus::AllowedFailure {
+        stderr.push_str(&format!("Failure allowed: {message}\n"));
+    }
+    if !record.stdout.is_empty() {
+        case.push_str(&format!(
+            "      <system-out>{}</system-out>\n",
+            escape(&record.stdout)
+        ));
+    }
+    if !stderr.is_empty() {
+        case.push_str(&format!(
+            "      <system-err>{}</system-err>\n",
+            escape(&stderr)
+        ));
+    }
+    case.push_str("    </testcase>\n");
+    case
+}
+
+fn test_suite(name: &str, time: Duration, cases: &[&TaskRecord], total: &mut Counts) -> String {
+    let mut counts = Counts::default();
+    let mut body = String::new();
+    for case in cases {
+        counts.add(case);
+        total.add(case);
+        body.push_str(&test_case(case, name));
+    }
+    format!(
+        "  <testsuite name=\"{}\" {} time=\"{}\">\n{}  </testsuite>\n",
+        escape(name),
+        counts.attributes(),
+        seconds(time),
+        body
+    )
+}
+
+/// The JUnit XML report of `records`
+pub(super) fn junit_xml(records: &[TaskRecord]) -> String {
+    let mut total = Counts::default();
+    let mut suites = String::new();
+
+    let root_cases = cases_of(records, None);
+    if !root_cases.is_empty() {
+        let time = root_cases.iter().map(|r| r.duration).sum();
+        suites.push_str(&test_suite(ROOT_SUITE, time, &root_cases, &mut total));
+    }
+    for (id, suite) in records.iter().enumerate().filter(|(_, r)| is_suite(r)) {
+        let cases = cases_of(records, Some(id));
+        suites.push_str(&test_suite(
+            &suite_name(records, id),
+            suite.duration,
+            &cases,
+            &mut total,
+        ));
+    }
+
+    let time = records
+        .iter()
+        .filter(|r| r.parent.is_none())
+        .map(|r| r.duration)
+        .sum();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{ROOT_SUITE}\" {} time=\"{}\">\n{}</testsuites>\n",
+        total.attributes(),
+        seconds(time),
+        suites
+    )
+}
@@ -0,0 +1,84 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use super::*;
+use crate::report::report_test::example_records;
+
+#[test]
+fn serial_and_parallel_tasks_become_suites() {
+    let mut records = example_records();
+    records[4].status = TaskStatus::Failed;
+    records[4].exit_status = Some(ExitStatus::from_raw(2 << 8));
+    records[4].error = Some("Shell script 'lint' exited with non-zero exit code.".to_string());
+    records[4].stderr = "src/main.rs: <unused> & \x1b[31mbad\x1b[0m\n".to_string();
+    records[6].status = TaskStatus::Skipped;
+
+    let xml = junit_xml(&records);
+    assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(xml.contains(
+        r#"<testsuites name="alchemist" tests="4" failures="1" errors="0" skipped="1" time="1.000">"#
+    ));
+    assert!(xml.contains(
+        r#"<testsuite name="run" tests="1" failures="0" errors="0" skipped="1" time="1.000">"#
+    ));
+    assert!(xml.contains(r#"<testsuite name="run/checks" tests="1""#));
+    assert!(xml.contains(r#"<testsuite name="run/checks/verify" tests="2" failures="1""#));
+    assert!(xml.contains(r#"<testcase name="lint" classname="run/checks/verify" time="0.200">"#));
+    assert!(xml.contains(
+        r#"<failure message="Shell script &apos;lint&apos; exited with non-zero exit code.">"#
+    ));
+    assert!(xml.contains("<system-err>src/main.rs: &lt;unused&gt; &amp; bad\n</system-err>"));
+    assert!(
+        xml.contains(
+            "<testcase name=\"deploy\" classname=\"run\" time=\"0.300\">\n      <skipped/>"
+        )
+    );
+}
+
+#[test]
+fn tasks_outside_of_suites_use_the_root_suite() {
+    let records = vec![
+        crate::report::report_test::record("build", "command", None, 1500),
+        crate::report::report_test::record("test", "shell", None, 500),
+    ];
+    let xml = junit_xml(&records);
+    assert!(xml.contains(
+        r#"<testsuite name="alchemist" tests="2" failures="0" errors="0" skipped="0" time="2.000">"#
+    ));
+    assert!(xml.contains(r#"<testcase name="test" classname="alchemist" time="0.500">"#));
+}
+
+#[test]
+fn skipped_suites_become_test_cases() {
+    let mut records = vec![
+        crate::report::report_test::record("build", "command", None, 1500),
+        crate::report::report_test::record("deploy", "serial", None, 0),
+    ];
+    records[0].status = TaskStatus::Failed;
+    records[1].status = TaskStatus::Skipped;
+    let xml = junit_xml(&records);
+    assert!(
+        xml.contains(
+            r#"<testsuite name="alchemist" tests="2" failures="1" errors="0" skipped="1""#
+        )
+    );
+    assert!(!xml.contains(r#"<testsuite name="deploy""#));
+}
+
+#[test]
+fn subtasks_of_other_tasks_are_cases_of_the_nearest_suite() {
+    let records = vec![
+        crate::report::report_test::record("ci", "serial", None, 1000),
+        crate::report::report_test::record("build", "command", Some(0), 600),
+        crate::report::report_test::record("build[t=x]", "command", Some(1), 300),
+        crate::report::report_test::record("build[t=y]", "command", Some(1), 300),
+        crate::report::report_test::record("deploy", "command", None, 400),
+        crate::report::report_test::record("teardown", "shell", Some(4), 100),
+    ];
+    let xml = junit_xml(&records);
+    assert!(xml.contains(r#"<testsuite name="ci" tests="3""#));
+    assert!(xml.contains(r#"<testcase name="build[t=x]" classname="ci""#));
+    assert!(xml.contains(r#"<testcase name="build[t=y]" classname="ci""#));
+    assert!(xml.contains(r#"<testsuite name="alchemist" tests="2""#));
+    assert!(xml.contains(r#"<testcase name="teardown" classname="alchemist""#));
+}
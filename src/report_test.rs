@@ -0,0 +1,84 @@
+use super::*;
+
+pub(super) fn record(
+    name: &str,
+    task_type: &str,
+    parent: Option<usize>,
+    millis: u64,
+) -> TaskRecord {
+    TaskRecord {
+        name: name.to_string(),
+        task_type: task_type.to_string(),
+        parent,
+        status: TaskStatus::Ok,
+        started: Instant::now(),
+        duration: Duration::from_millis(millis),
+        exit_status: None,
+        error: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+/// run → (build ∥ (lint → test)) → deploy
+pub(super) fn example_records() -> Vec<TaskRecord> {
+    vec![
+        record("run", "serial", None, 1000),
+        record("checks", "parallel", Some(0), 700),
+        record("build", "command", Some(1), 300),
+        record("verify", "serial", Some(1), 700),
+        record("lint", "command", Some(3), 200),
+        record("test", "command", Some(3), 500),
+        record("deploy", "command", Some(0), 300),
+    ]
+}
+
+#[test]
+fn critical_path_follows_slowest_parallel_subtask() {
+    let records = example_records();
+    assert_eq!(critical_path(&records), vec![0, 1, 3, 4, 5, 6]);
+}
+
+#[test]
+fn critical_path_ignores_skipped_tasks() {
+    let mut records = example_records();
+    records[6].status = TaskStatus::Skipped;
+    records.push(record("other", "command", None, 0));
+    records[7].status = TaskStatus::Skipped;
+    assert_eq!(critical_path(&records), vec![0, 1, 3, 4, 5]);
+}
+
+#[test]
+fn summary_lists_nested_tasks() {
+    let mut records = example_records();
+    records[2].status = TaskStatus::Failed;
+    records[2].exit_status = Some(ExitStatus::from_raw(1 << 8));
+
+    let summary = summary(&records, true);
+    let lines = summary.lines().collect::<Vec<_>>();
+    assert!(lines[1].contains("run"));
+    assert!(lines[3].contains("    build"));
+    assert!(lines[3].contains("failed"));
+    assert!(lines[3].ends_with("  1"));
+    assert!(lines[6].contains("      test"));
+    assert!(summary.contains("lint (200ms) → test (500ms) → deploy (300ms)"));
+    assert!(summary.contains("(1.00s total)"));
+}
+
+#[test]
+fn format_durations() {
+    assert_eq!(format_duration(Duration::from_millis(42)), "42ms");
+    assert_eq!(format_duration(Duration::from_millis(1234)), "1.23s");
+    assert_eq!(format_duration(Duration::from_secs(125)), "2m05s");
+}
+
+#[test]
+fn running_tasks_are_cancelled_when_the_run_finishes() {
+    let mut records = example_records();
+    records[0].status = TaskStatus::Running;
+    records[3].status = TaskStatus::Running;
+    cancel_running(&mut records);
+    assert_eq!(records[0].status, TaskStatus::Cancelled);
+    assert_eq!(records[3].status, TaskStatus::Cancelled);
+    assert_eq!(records[2].status, TaskStatus::Ok);
+}
@@ -2,16 +2,33 @@
 #[path = "tasks_test.rs"]
 mod tasks_test;
 
-use std::collections::HashMap;
-use std::process::Command;
+mod condition;
+pub(crate) mod matrix;
+mod variables;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{Receiver, channel};
+use std::time::{Duration, Instant};
 
 use crate::config::AlchemistConfig;
 use crate::error::{AlchemistError, AssertionError, Result, ResultContext};
 
 use crate::cli::terminal;
+use crate::process::{self, TaskLog, TrackedChild};
+use crate::report::{self, TaskStatus};
+use condition::Conditions;
+use matrix::{Matrix, MatrixValues};
+use variables::Capture;
 
 use owo_colors::OwoColorize;
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize, Serializer};
 
 // -- end of imports --
 
@@ -32,15 +49,35 @@ pub trait RunnableTask {
 /// command = "echo"
 /// args = ["hello", "world"]
 /// ```
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct AlchemistBasicTask {
     #[allow(dead_code)]
     command: String,
     #[allow(dead_code)]
-    args: Option<Vec<String>>,
-    env: Option<HashMap<String, String>>,
+    pub(crate) args: Option<Vec<String>>,
+    #[serde(serialize_with = "serialize_sorted")]
+    pub(crate) env: Option<HashMap<String, String>>,
+    ok_exit_codes: Option<Vec<i32>>,
+    capture: Option<String>,
+    stdin: Option<String>,
+    stdout: Option<String>,
+    matrix: Option<Matrix>,
+    matrix_parallel: Option<bool>,
+    /// The values of the matrix instance this task is
+    #[serde(skip)]
+    matrix_values: MatrixValues,
     pub hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
+    log_file: Option<String>,
 }
 
 impl From<AlchemistBasicTask> for AlchemistTaskType {
@@ -61,12 +98,21 @@ impl From<AlchemistBasicTask> for AlchemistTaskType {
 /// hide = false
 ///
 /// ```
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct AlchemistSerialTasks {
     #[allow(dead_code)]
     serial_tasks: Vec<String>,
     hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
 }
 
 impl From<AlchemistSerialTasks> for AlchemistTaskType {
@@ -87,11 +133,20 @@ impl From<AlchemistSerialTasks> for AlchemistTaskType {
 /// hide = false
 ///
 /// ```
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct AlchemistParallelTasks {
     parallel_tasks: Vec<String>,
     hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
 }
 
 impl From<AlchemistParallelTasks> for AlchemistTaskType {
@@ -118,11 +173,28 @@ impl From<AlchemistParallelTasks> for AlchemistTaskType {
 /// '''
 ///
 /// ```
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct AlchemistShellTask {
     shell_script: String,
+    ok_exit_codes: Option<Vec<i32>>,
+    capture: Option<String>,
+    matrix: Option<Matrix>,
+    matrix_parallel: Option<bool>,
+    /// The values of the matrix instance this task is
+    #[serde(skip)]
+    matrix_values: MatrixValues,
     hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
+    log_file: Option<String>,
 }
 
 impl From<AlchemistShellTask> for AlchemistTaskType {
@@ -131,30 +203,431 @@ impl From<AlchemistShellTask> for AlchemistTaskType {
     }
 }
 
-impl RunnableTask for AlchemistBasicTask {
-    fn run<S: ToString>(&self, task_name: S, _config: &AlchemistConfig) -> Result<()> {
-        let task_name = task_name.to_string();
-        let mut cmd = Command::new(&self.command);
+/// Alchemist PipelineTask type connects the stdout of each task to the stdin of the next
+///
+/// The stages run at the same time, like a shell pipeline with `pipefail`:
+/// the pipeline fails when any stage fails. Only basic and shell tasks can
+/// be stages.
+///
+/// Example:
+/// ```
+/// [tasks.my_task]
+/// pipeline = ["gen_data", "transform", "upload"]
+/// ```
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AlchemistPipelineTask {
+    pipeline: Vec<String>,
+    hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
+}
+
+impl From<AlchemistPipelineTask> for AlchemistTaskType {
+    fn from(task: AlchemistPipelineTask) -> Self {
+        AlchemistTaskType::AlchemistPipelineTask(task)
+    }
+}
+
+/// Alchemist ServiceTask type starts a long-running command in the background
+///
+/// The task is finished once the service is ready (see [`AlchemistReadinessCheck`]),
+/// so tasks after it in a serial task can rely on it. Services are stopped
+/// when alchemist is done running the given tasks.
+///
+/// Example:
+/// ```
+/// [tasks.server]
+/// service = "./target/debug/server"
+/// args = ["--port", "8080"]
+/// ready = { port = 8080 }
+/// ```
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AlchemistServiceTask {
+    service: String,
+    args: Option<Vec<String>>,
+    #[serde(serialize_with = "serialize_sorted")]
+    env: Option<HashMap<String, String>>,
+    ready: Option<AlchemistReadinessCheck>,
+    hide: Option<bool>,
+    aliases: Option<Vec<String>>,
+    watch: Option<Vec<String>>,
+    allow_failure: Option<bool>,
+    finally: Option<Vec<String>>,
+    on_failure: Option<Vec<String>>,
+    #[serde(rename = "if")]
+    run_if: Option<String>,
+    unless: Option<String>,
+    platform: Option<Vec<String>>,
+    log_file: Option<String>,
+}
+
+impl From<AlchemistServiceTask> for AlchemistTaskType {
+    fn from(task: AlchemistServiceTask) -> Self {
+        AlchemistTaskType::AlchemistServiceTask(task)
+    }
+}
+
+/// How to tell that a service is ready, all given checks have to pass
+///
+/// Example:
+/// ```
+/// ready = { http = "http://localhost:8080/health", timeout = 60 }
+/// ```
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AlchemistReadinessCheck {
+    /// A TCP port on localhost that accepts connections
+    port: Option<u16>,
+    /// A `http://` url that responds with status 200
+    http: Option<String>,
+    /// A regex matching a line of output of the service
+    log: Option<String>,
+    /// A command (run in `sh`) that exits successfully
+    command: Option<String>,
+    /// Seconds to wait for the service to become ready (default 30)
+    timeout: Option<u64>,
+}
+
+const SERVICE_READY_TIMEOUT_SECS: u64 = 30;
+const SERVICE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether `url` (`http://host[:port]/path`) responds with status 200
+fn http_ok(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = if path.is_empty() { "/" } else { path };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let Ok(mut stream) = TcpStream::connect(address) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+    if write!(
+        stream,
+        "GET {path} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n"
+    )
+    .is_err()
+    {
+        return false;
+    }
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line).is_ok()
+        && status_line.split_whitespace().nth(1) == Some("200")
+}
+
+impl AlchemistReadinessCheck {
+    /// Waits until all checks pass, failing when the service exits or takes too long
+    fn wait_until_ready(
+        &self,
+        task_name: &str,
+        child: &mut TrackedChild,
+        log_matched: Receiver<()>,
+        project_dir: Option<&PathBuf>,
+    ) -> Result<()> {
+        let timeout = Duration::from_secs(self.timeout.unwrap_or(SERVICE_READY_TIMEOUT_SECS));
+        let started = Instant::now();
+        let mut log_seen = self.log.is_none();
+        loop {
+            log_seen = log_seen || log_matched.try_recv().is_ok();
+            let port_open = self.port.is_none_or(|port| {
+                TcpStream::connect_timeout(
+                    &SocketAddr::from(([127, 0, 0, 1], port)),
+                    SERVICE_POLL_INTERVAL,
+                )
+                .is_ok()
+            });
+            let ready = log_seen
+                && port_open
+                && self.http.as_deref().is_none_or(http_ok)
+                && self.command.as_ref().is_none_or(|command| {
+                    let mut cmd = Command::new("sh");
+                    if let Some(project_dir) = project_dir {
+                        cmd.current_dir(project_dir);
+                    }
+                    cmd.arg("-c")
+                        .arg(command)
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .status()
+                        .is_ok_and(|status| status.success())
+                });
+            if ready {
+                return Ok(());
+            }
+
+            if let Some(status) = child.try_wait().error_msg(format!(
+                "Service '{task_name}' can not be checked (won't respond to waitpid)."
+            ))? {
+                return AssertionError(format!(
+                    "Service '{task_name}' exited ({status}) before it was ready."
+                ))
+                .into();
+            }
+            if started.elapsed() > timeout {
+                return AssertionError(format!(
+                    "Service '{task_name}' was not ready within {} seconds.",
+                    timeout.as_secs()
+                ))
+                .into();
+            }
+            std::thread::sleep(SERVICE_POLL_INTERVAL);
+        }
+    }
+
+    fn describe(&self) -> Vec<String> {
+        let mut checks = Vec::new();
+        if let Some(port) = self.port {
+            checks.push(format!("port {port} open"));
+        }
+        if let Some(http) = &self.http {
+            checks.push(format!("{http} responds with 200"));
+        }
+        if let Some(log) = &self.log {
+            checks.push(format!("output matches /{log}/"));
+        }
+        if let Some(command) = &self.command {
+            checks.push(format!("`{command}` succeeds"));
+        }
+        checks
+    }
+}
+
+/// Whether a process exited successfully, `ok_exit_codes` replaces the default of `[0]`
+fn is_ok_exit(status: &ExitStatus, ok_exit_codes: Option<&Vec<i32>>) -> bool {
+    match ok_exit_codes {
+        Some(ok_exit_codes) => status.code().is_some_and(|c| ok_exit_codes.contains(&c)),
+        None => status.success(),
+    }
+}
+
+fn substitute_args(args: &[String]) -> Vec<String> {
+    args.iter().map(|arg| variables::substitute(arg)).collect()
+}
+
+fn substitute_env(env: &HashMap<String, String>) -> HashMap<String, String> {
+    env.iter()
+        .map(|(key, value)| (key.clone(), variables::substitute(value)))
+        .collect()
+}
+
+/// Serializes `map` with its keys in order, so `--explain` shows a task the same every time
+fn serialize_sorted<S: Serializer>(
+    map: &Option<HashMap<String, String>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    map.as_ref()
+        .map(|map| map.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
+/// The process of a basic or shell task, before it is spawned
+struct TaskProcess<'a> {
+    cmd: Command,
+    ok_exit_codes: Option<&'a Vec<i32>>,
+    capture: Option<&'a String>,
+    log_file: Option<&'a String>,
+    stdin: Option<&'a String>,
+    stdout: Option<&'a String>,
+}
+
+impl TaskProcess<'_> {
+    /// Checks the fields of the process and opens the files it is redirected to
+    fn prepare(&mut self, task_name: &str, config: &AlchemistConfig) -> Result<()> {
+        if let Some(name) = self.capture {
+            if !variables::is_valid_name(name) {
+                return AssertionError(format!(
+                    "Task '{task_name}' captures into '{name}', which is not a valid variable name."
+                ))
+                .into();
+            }
+            if self.stdout.is_some() {
+                return AssertionError(format!(
+                    "Task '{task_name}' can not both capture and redirect its stdout."
+                ))
+                .into();
+            }
+        }
+        if let Some(stdin) = self.stdin {
+            let path = project_path(stdin, config);
+            self.cmd.stdin(File::open(&path).error_msg(format!(
+                "Can not read the stdin '{}' of task {task_name}.",
+                path.display()
+            ))?);
+        }
+        if let Some(stdout) = self.stdout {
+            let path = project_path(stdout, config);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).error_msg(format!(
+                    "Can not create the directory of the stdout '{}' of task {task_name}.",
+                    path.display()
+                ))?;
+            }
+            self.cmd.stdout(File::create(&path).error_msg(format!(
+                "Can not create the stdout '{}' of task {task_name}.",
+                path.display()
+            ))?);
+        }
+        Ok(())
+    }
+
+    /// Spawns the process, piping its stdout to the caller when `pipe_stdout`
+    /// and collecting it when it is captured into a variable
+    fn spawn(
+        &mut self,
+        task_name: &str,
+        log: Option<TaskLog>,
+        pipe_stdout: bool,
+    ) -> std::io::Result<(TrackedChild, Capture)> {
+        let output = Capture::default();
+        if pipe_stdout {
+            self.cmd.stdout(Stdio::piped());
+        }
+        let child = if pipe_stdout || self.stdout.is_some() {
+            TrackedChild::spawn_keeping_stdout(&mut self.cmd, task_name, log)?
+        } else if self.capture.is_some() {
+            let output = output.clone();
+            TrackedChild::spawn_with_output(&mut self.cmd, task_name, log, move |stream, line| {
+                output.add_line(stream, line)
+            })?
+        } else {
+            TrackedChild::spawn(&mut self.cmd, task_name, log)?
+        };
+        Ok((child, output))
+    }
+}
+
+fn store_capture(capture: Option<&String>, output: &Capture) {
+    if let Some(name) = capture {
+        let value = output.value();
+        terminal::verbose(1, format!("  captured {name}={value}"));
+        variables::set(name.as_str(), value.as_str());
+    }
+}
+
+/// `path` relative to the project of `config`
+fn project_path(path: &str, config: &AlchemistConfig) -> PathBuf {
+    match &config.project_dir {
+        Some(project_dir) => project_dir.join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Opens the log of `task_name`: its `log_file` or a file named after it in `--log-dir`
+///
+/// A relative `log_file` is relative to the project of the task.
+fn open_task_log(
+    log_file: Option<&String>,
+    task_name: &str,
+    config: &AlchemistConfig,
+) -> Result<Option<TaskLog>> {
+    let path = match (log_file, process::log_dir()) {
+        (Some(log_file), _) => project_path(log_file, config),
+        (None, Some(log_dir)) => log_dir.join(format!(
+            "{}.log",
+            task_name.replace(|c: char| !c.is_alphanumeric() && !"-_.".contains(c), "_")
+        )),
+        (None, None) => return Ok(None),
+    };
+    let log = TaskLog::create(&path).error_msg(format!(
+        "Can not create the log file '{}' of task {task_name}.",
+        path.display()
+    ))?;
+    Ok(Some(log))
+}
+
+impl AlchemistBasicTask {
+    /// The instance of this matrix task with `values`, the hooks and
+    /// `allow_failure` belong to the matrix task as a whole
+    fn instance(&self, values: &MatrixValues) -> Self {
+        let interpolate = |text: &String| matrix::interpolate(text, values);
+        Self {
+            command: interpolate(&self.command),
+            args: self
+                .args
+                .as_ref()
+                .map(|args| args.iter().map(interpolate).collect()),
+            env: self.env.as_ref().map(|env| {
+                env.iter()
+                    .map(|(key, value)| (key.clone(), interpolate(value)))
+                    .collect()
+            }),
+            capture: self.capture.as_ref().map(interpolate),
+            stdin: self.stdin.as_ref().map(interpolate),
+            stdout: self.stdout.as_ref().map(interpolate),
+            log_file: self.log_file.as_ref().map(interpolate),
+            matrix: None,
+            matrix_values: values.clone(),
+            allow_failure: None,
+            finally: None,
+            on_failure: None,
+            ..self.clone()
+        }
+    }
+
+    /// The process of this task with its variables substituted, and how it is shown
+    fn process(&self, config: &AlchemistConfig) -> (TaskProcess<'_>, String) {
+        let command = variables::substitute(&self.command);
+        let mut cmd = Command::new(&command);
 
+        if let Some(project_dir) = &config.project_dir {
+            cmd.current_dir(project_dir);
+        }
+        variables::export(&mut cmd);
+        cmd.envs(self.matrix_values.iter().cloned());
         if let Some(env_var) = &self.env {
-            cmd.envs(env_var);
+            cmd.envs(substitute_env(env_var));
         }
 
         let command_str = if let Some(args) = &self.args {
-            cmd.args(args);
-            format!("{} {}", &self.command, args.join(" "))
+            let args = substitute_args(args);
+            cmd.args(&args);
+            format!("{} {}", command, args.join(" "))
         } else {
-            self.command.to_string()
+            command
+        };
+        let process = TaskProcess {
+            cmd,
+            ok_exit_codes: self.ok_exit_codes.as_ref(),
+            capture: self.capture.as_ref(),
+            log_file: self.log_file.as_ref(),
+            stdin: self.stdin.as_ref(),
+            stdout: self.stdout.as_ref(),
         };
+        (process, command_str)
+    }
+}
+
+impl RunnableTask for AlchemistBasicTask {
+    fn run<S: ToString>(&self, task_name: S, config: &AlchemistConfig) -> Result<()> {
+        let task_name = task_name.to_string();
+        let (mut process, command_str) = self.process(config);
         terminal::info(format!("Running command {}", command_str));
-        let mut child = cmd.spawn().error_msg(format!("Starting basic task {task_name} with command `{command_str}` either not found or insufficient permissions to run."))?;
+        process.prepare(&task_name, config)?;
+        let log = open_task_log(process.log_file, &task_name, config)?;
+        let (mut child, output) = process.spawn(&task_name, log, false).error_msg(format!("Starting basic task {task_name} with command `{command_str}` either not found or insufficient permissions to run."))?;
         let exit_code = child.wait().error_msg(format!("While running basic task {task_name}, command `{command_str}` failed to wait(pid) on started process."))?;
+        report::exit_status(&exit_code);
 
-        if !exit_code.success() {
+        if !is_ok_exit(&exit_code, process.ok_exit_codes) {
             return AssertionError(
                 format!("While running basic task {task_name}, command `{command_str}` failed (non-zero exit code).")
             ).into();
         }
+        store_capture(process.capture, &output);
         terminal::ok(format!("Finished command {}", command_str));
         Ok(())
     }
@@ -171,6 +644,106 @@ impl RunnableTask for AlchemistBasicTask {
     }
 }
 
+/// Runs the `kind` hooks of `task_name` one after another, all of them even when one fails
+fn run_hooks(
+    kind: &str,
+    task_name: &str,
+    hooks: &[String],
+    config: &AlchemistConfig,
+) -> Result<()> {
+    terminal::info(format!("Running {kind} tasks of {task_name}: {hooks:?}"));
+    let mut result = Ok(());
+    for hook in hooks {
+        let hook_result = config
+            .resolve_task(hook)?
+            .ok_or::<AlchemistError>(
+                AssertionError(format!(
+                    "Task '{task_name}' has an invalid {kind} task '{hook}'"
+                ))
+                .into(),
+            )
+            .and_then(|(task_config, task)| task.run(hook, &task_config));
+        match hook_result {
+            Err(e) if result.is_ok() => result = Err(e),
+            Err(e) => terminal::error(e),
+            Ok(()) => {}
+        }
+    }
+    result
+}
+
+/// Runs the instances of the matrix task `task_name`, one after another or in parallel
+fn run_matrix(
+    task_name: &str,
+    instances: Vec<(String, AlchemistTaskType)>,
+    parallel: bool,
+    config: &AlchemistConfig,
+) -> Result<()> {
+    terminal::info(format!(
+        "Running {} instances of matrix task '{task_name}'{}",
+        instances.len(),
+        if parallel { " in parallel" } else { "" }
+    ));
+    if parallel {
+        let parent = report::current();
+        let cleaning_up = process::is_cleaning_up();
+        let results = std::thread::scope(|scope| {
+            instances
+                .iter()
+                .map(|(name, task)| {
+                    scope.spawn(move || {
+                        report::set_current(parent);
+                        process::set_cleaning_up(cleaning_up);
+                        task.run(name, config)
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|h| h.join().expect("Can not join thread"))
+                .collect::<Vec<_>>()
+        });
+        let mut failed = 0;
+        for result in results {
+            if let Err(e) = result {
+                terminal::error(e);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            return AssertionError(format!(
+                "{failed} of {} instances of matrix task '{task_name}' failed.",
+                instances.len()
+            ))
+            .into();
+        }
+    } else {
+        for (i, (name, task)) in instances.iter().enumerate() {
+            let result = if process::is_cancelled() {
+                AssertionError(format!("Matrix task '{task_name}' was cancelled.")).into()
+            } else {
+                task.run(name, config)
+            };
+            if result.is_err() {
+                for (name, task) in &instances[i + 1..] {
+                    report::skip(name, &task.describe().task_type);
+                }
+                return result;
+            }
+        }
+    }
+    terminal::ok(format!("Finished matrix task '{task_name}'"));
+    Ok(())
+}
+
+/// Records the given tasks as skipped in the run summary
+pub fn skip_tasks(task_names: &[String], config: &AlchemistConfig) {
+    for task_name in task_names {
+        if let Ok(Some((_, task))) = config.resolve_task(task_name) {
+            report::skip(task_name, &task.describe().task_type);
+        }
+    }
+}
+
 impl RunnableTask for AlchemistSerialTasks {
     fn run<S: ToString>(&self, task_name: S, config: &AlchemistConfig) -> Result<()> {
         let task_name = task_name.to_string();
@@ -178,14 +751,24 @@ impl RunnableTask for AlchemistSerialTasks {
             "Running serial task '{}' which is a collection of {:?}",
             task_name, self.serial_tasks
         ));
-        for sub_task_name in &self.serial_tasks {
-            let task = config.tasks.get(sub_task_name).ok_or::<AlchemistError>(
-                AssertionError(format!(
-                    "Serial task '{task_name}' has an invalid subtask '{sub_task_name}'"
-                ))
-                .into(),
-            )?;
-            task.run(sub_task_name, config)?
+        for (i, sub_task_name) in self.serial_tasks.iter().enumerate() {
+            let result = if process::is_cancelled() {
+                AssertionError(format!("Serial task '{task_name}' was cancelled.")).into()
+            } else {
+                config
+                    .resolve_task(sub_task_name)?
+                    .ok_or::<AlchemistError>(
+                        AssertionError(format!(
+                            "Serial task '{task_name}' has an invalid subtask '{sub_task_name}'"
+                        ))
+                        .into(),
+                    )
+                    .and_then(|(task_config, task)| task.run(sub_task_name, &task_config))
+            };
+            if result.is_err() {
+                skip_tasks(&self.serial_tasks[i + 1..], config);
+                return result;
+            }
         }
         terminal::ok(format!("Finished serial task '{task_name}'"));
         Ok(())
@@ -211,13 +794,21 @@ impl RunnableTask for AlchemistParallelTasks {
             task_name, self.parallel_tasks
         ));
         let mut background_jobs = Vec::<std::thread::JoinHandle<crate::error::Result<()>>>::new();
-        for sub_task_name in &self.parallel_tasks {
-            match config.tasks.get(sub_task_name) {
-                Some(task) => {
-                    let ctask = task.clone();
-                    let cfg = config.clone();
+        for (i, sub_task_name) in self.parallel_tasks.iter().enumerate() {
+            if process::is_cancelled() {
+                skip_tasks(&self.parallel_tasks[i..], config);
+                break;
+            }
+            match config.resolve_task(sub_task_name)? {
+                Some((task_config, task)) => {
+                    let ctask = task;
+                    let cfg = task_config.into_owned();
                     let name = sub_task_name.clone();
+                    let parent = report::current();
+                    let cleaning_up = process::is_cleaning_up();
                     background_jobs.push(std::thread::spawn(move || -> Result<()> {
+                        report::set_current(parent);
+                        process::set_cleaning_up(cleaning_up);
                         ctask.run(name, &cfg)?;
                         Ok(())
                     }));
@@ -243,6 +834,8 @@ impl RunnableTask for AlchemistParallelTasks {
         }
         if has_error {
             AssertionError("One or more errors occoured in parallel tasks".into()).into()
+        } else if process::is_cancelled() {
+            AssertionError(format!("Parallel task '{task_name}' was cancelled.")).into()
         } else {
             terminal::ok(format!("Finished parallel task '{task_name}'"));
             Ok(())
@@ -260,28 +853,69 @@ impl RunnableTask for AlchemistParallelTasks {
     }
 }
 
-impl RunnableTask for AlchemistShellTask {
-    fn run<S: ToString>(&self, task_name: S, _config: &AlchemistConfig) -> Result<()> {
-        let task_name = task_name.to_string();
+impl AlchemistShellTask {
+    /// The instance of this matrix task with `values`, the script gets them
+    /// as environment variables
+    fn instance(&self, values: &MatrixValues) -> Self {
+        let interpolate = |text: &String| matrix::interpolate(text, values);
+        Self {
+            capture: self.capture.as_ref().map(interpolate),
+            log_file: self.log_file.as_ref().map(interpolate),
+            matrix: None,
+            matrix_values: values.clone(),
+            allow_failure: None,
+            finally: None,
+            on_failure: None,
+            ..self.clone()
+        }
+    }
+
+    fn process(&self, config: &AlchemistConfig) -> TaskProcess<'_> {
         let mut cmd = Command::new("sh");
 
+        if let Some(project_dir) = &config.project_dir {
+            cmd.current_dir(project_dir);
+        }
+
+        variables::export(&mut cmd);
+        cmd.envs(self.matrix_values.iter().cloned());
+
         cmd.arg("-c");
         cmd.arg(&self.shell_script);
+        TaskProcess {
+            cmd,
+            ok_exit_codes: self.ok_exit_codes.as_ref(),
+            capture: self.capture.as_ref(),
+            log_file: self.log_file.as_ref(),
+            stdin: None,
+            stdout: None,
+        }
+    }
+}
+
+impl RunnableTask for AlchemistShellTask {
+    fn run<S: ToString>(&self, task_name: S, config: &AlchemistConfig) -> Result<()> {
+        let task_name = task_name.to_string();
+        let mut process = self.process(config);
 
         terminal::info(format!("Running shell script {}", task_name));
-        let mut child = cmd
-            .spawn()
+        process.prepare(&task_name, config)?;
+        let log = open_task_log(process.log_file, &task_name, config)?;
+        let (mut child, output) = process
+            .spawn(&task_name, log, false)
             .error_msg(format!("Failed to start shell script {task_name}."))?;
         let exit_code = child.wait().error_msg(format!(
             "Shell script '{task_name}' can not be awaited (won't stop)."
         ))?;
+        report::exit_status(&exit_code);
 
-        if !exit_code.success() {
+        if !is_ok_exit(&exit_code, process.ok_exit_codes) {
             return AssertionError(format!(
                 "Shell script '{task_name}' exited with non-zero exit code."
             ))
             .into();
         }
+        store_capture(process.capture, &output);
         terminal::ok(format!("Finished shell script {task_name}"));
         Ok(())
     }
@@ -294,8 +928,249 @@ impl RunnableTask for AlchemistShellTask {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// A spawned stage of a pipeline
+struct PipelineStage {
+    name: String,
+    child: TrackedChild,
+    ok_exit_codes: Option<Vec<i32>>,
+    capture: Option<String>,
+    output: Capture,
+}
+
+fn describe_exit(status: &ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exit code {code}"),
+        (None, Some(signal)) => format!("killed by signal {signal}"),
+        (None, None) => "unknown exit status".to_string(),
+    }
+}
+
+impl AlchemistPipelineTask {
+    /// Spawns all stages, connecting the stdout of each stage to the stdin of the next
+    ///
+    /// Every stage is resolved and checked before any of them is spawned.
+    fn spawn_stages(
+        &self,
+        task_name: &str,
+        config: &AlchemistConfig,
+    ) -> Result<Vec<PipelineStage>> {
+        if self.pipeline.is_empty() {
+            return AssertionError(format!("Pipeline '{task_name}' has no stages.")).into();
+        }
+        let resolved = self
+            .pipeline
+            .iter()
+            .map(|stage_name| {
+                config.resolve_task(stage_name)?.ok_or::<AlchemistError>(
+                    AssertionError(format!(
+                        "Pipeline '{task_name}' has an invalid stage '{stage_name}'"
+                    ))
+                    .into(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut processes = Vec::new();
+        for (i, (stage_name, (stage_config, stage))) in
+            self.pipeline.iter().zip(&resolved).enumerate()
+        {
+            let is_last = i + 1 == self.pipeline.len();
+            let process = match stage {
+                AlchemistTaskType::AlchemistBasicTask(task) => task.process(stage_config).0,
+                AlchemistTaskType::AlchemistShellTask(task) => task.process(stage_config),
+                other => {
+                    return AssertionError(format!(
+                        "Pipeline '{task_name}' has a stage '{stage_name}' of type {}, only command and shell tasks can be piped.",
+                        other.describe().task_type
+                    ))
+                    .into();
+                }
+            };
+            if i > 0 && process.stdin.is_some() {
+                return AssertionError(format!(
+                    "Stage '{stage_name}' of pipeline '{task_name}' redirects its stdin, only the first stage can."
+                ))
+                .into();
+            }
+            if !is_last && (process.stdout.is_some() || process.capture.is_some()) {
+                return AssertionError(format!(
+                    "Stage '{stage_name}' of pipeline '{task_name}' redirects or captures its stdout, only the last stage can."
+                ))
+                .into();
+            }
+            processes.push(process);
+        }
+        let mut logs = Vec::new();
+        for ((stage_name, (stage_config, _)), process) in
+            self.pipeline.iter().zip(&resolved).zip(&mut processes)
+        {
+            process.prepare(stage_name, stage_config)?;
+            logs.push(open_task_log(process.log_file, stage_name, stage_config)?);
+        }
+
+        let mut stages = Vec::<PipelineStage>::new();
+        for (i, ((stage_name, mut process), log)) in
+            self.pipeline.iter().zip(processes).zip(logs).enumerate()
+        {
+            let is_last = i + 1 == self.pipeline.len();
+            if let Some(previous) = stages.last_mut()
+                && let Some(stdout) = previous.child.stdout.take()
+            {
+                process.cmd.stdin(stdout);
+            }
+            let (child, output) = process.spawn(stage_name, log, !is_last).error_msg(format!(
+                "Can not start stage '{stage_name}' of pipeline '{task_name}'."
+            ))?;
+            stages.push(PipelineStage {
+                name: stage_name.clone(),
+                child,
+                ok_exit_codes: process.ok_exit_codes.cloned(),
+                capture: process.capture.cloned(),
+                output,
+            });
+        }
+        Ok(stages)
+    }
+}
+
+impl RunnableTask for AlchemistPipelineTask {
+    fn run<S: ToString>(&self, task_name: S, config: &AlchemistConfig) -> Result<()> {
+        let task_name = task_name.to_string();
+        terminal::info(format!(
+            "Running pipeline '{task_name}': {}",
+            self.pipeline.join(" | ")
+        ));
+        let mut failed = Vec::new();
+        let mut last_status = None;
+        for mut stage in self.spawn_stages(&task_name, config)? {
+            let status = stage.child.wait().error_msg(format!(
+                "Stage '{}' of pipeline '{task_name}' can not be awaited (won't stop).",
+                stage.name
+            ))?;
+            let ok = is_ok_exit(&status, stage.ok_exit_codes.as_ref());
+            if ok {
+                store_capture(stage.capture.as_ref(), &stage.output);
+            } else {
+                failed.push(format!("'{}' ({})", stage.name, describe_exit(&status)));
+            }
+            // Like `pipefail` the status of the last failing stage is reported
+            if !ok || failed.is_empty() {
+                last_status = Some(status);
+            }
+        }
+        if let Some(status) = last_status {
+            report::exit_status(&status);
+        }
+
+        if !failed.is_empty() {
+            return AssertionError(format!(
+                "Pipeline '{task_name}' failed at stage {}.",
+                failed.join(", ")
+            ))
+            .into();
+        }
+        terminal::ok(format!("Finished pipeline '{task_name}'"));
+        Ok(())
+    }
+
+    fn describe(&self) -> TaskDescription {
+        TaskDescription {
+            task_type: "pipeline".to_string(),
+            description: vec![format!("{}", self.pipeline.join(&" | ".blue().to_string()))],
+        }
+    }
+}
+
+impl RunnableTask for AlchemistServiceTask {
+    fn run<S: ToString>(&self, task_name: S, config: &AlchemistConfig) -> Result<()> {
+        let task_name = task_name.to_string();
+        let service = variables::substitute(&self.service);
+        let mut cmd = Command::new(&service);
+
+        if let Some(project_dir) = &config.project_dir {
+            cmd.current_dir(project_dir);
+        }
+        variables::export(&mut cmd);
+        if let Some(env_var) = &self.env {
+            cmd.envs(substitute_env(env_var));
+        }
+        let command_str = if let Some(args) = &self.args {
+            let args = substitute_args(args);
+            cmd.args(&args);
+            format!("{} {}", service, args.join(" "))
+        } else {
+            service
+        };
+
+        let log_pattern = self
+            .ready
+            .as_ref()
+            .and_then(|ready| ready.log.as_ref())
+            .map(|log| {
+                Regex::new(log).map_err(|e| {
+                    AssertionError(format!(
+                        "Service '{task_name}' has an invalid log pattern ({e})."
+                    ))
+                })
+            })
+            .transpose()?;
+        terminal::info(format!("Starting service {task_name}: {command_str}"));
+        let log = open_task_log(self.log_file.as_ref(), &task_name, config)?;
+        let (log_sender, log_matched) = channel();
+        let spawned = match log_pattern {
+            Some(pattern) => {
+                TrackedChild::spawn_with_output(&mut cmd, &task_name, log, move |_, line| {
+                    if pattern.is_match(line) {
+                        let _ = log_sender.send(());
+                    }
+                })
+            }
+            None => TrackedChild::spawn(&mut cmd, &task_name, log),
+        };
+        let mut child = spawned.error_msg(format!("Starting service {task_name} with command `{command_str}` either not found or insufficient permissions to run."))?;
+
+        if let Some(ready) = &self.ready
+            && let Err(e) = ready.wait_until_ready(
+                &task_name,
+                &mut child,
+                log_matched,
+                config.project_dir.as_ref(),
+            )
+        {
+            child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+
+        terminal::ok(format!("Service {task_name} is ready"));
+        process::keep_service(task_name, child);
+        Ok(())
+    }
+
+    fn describe(&self) -> TaskDescription {
+        let mut description = vec![format!(
+            "{} {}",
+            self.service,
+            self.args.as_ref().unwrap_or(&vec![]).join(" ")
+        )];
+        if let Some(ready) = &self.ready {
+            description.extend(
+                ready
+                    .describe()
+                    .into_iter()
+                    .map(|c| format!("ready when {c}")),
+            );
+        }
+        TaskDescription {
+            task_type: "service".to_string(),
+            description,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(untagged)]
+#[allow(clippy::enum_variant_names)]
 /// An enum of multiple variations of tasks within the alchemist.toml
 ///
 ///
@@ -307,27 +1182,276 @@ pub enum AlchemistTaskType {
     AlchemistSerialTasks(AlchemistSerialTasks),
     AlchemistParallelTasks(AlchemistParallelTasks),
     AlchemistShellTask(AlchemistShellTask),
+    AlchemistServiceTask(AlchemistServiceTask),
+    AlchemistPipelineTask(AlchemistPipelineTask),
+}
+
+/// Why a task definition could not be read as a specific task type
+pub struct TaskTypeMismatch {
+    /// Name of the task type, as shown in `--list`
+    pub task_type: &'static str,
+    /// The field that identifies this task type
+    pub key: &'static str,
+    pub reason: String,
+}
+
+fn type_mismatch<T: serde::de::DeserializeOwned>(
+    value: &toml::Value,
+    task_type: &'static str,
+    key: &'static str,
+) -> Option<TaskTypeMismatch> {
+    value
+        .clone()
+        .try_into::<T>()
+        .err()
+        .map(|e| TaskTypeMismatch {
+            task_type,
+            key,
+            reason: e.message().trim().to_string(),
+        })
 }
 
 impl AlchemistTaskType {
+    /// Tries to read `value` as every task type, returning why each one failed
+    ///
+    /// An empty result means the value is a valid task.
+    pub fn mismatches(value: &toml::Value) -> Vec<TaskTypeMismatch> {
+        let mismatches = [
+            type_mismatch::<AlchemistBasicTask>(value, "command", "command"),
+            type_mismatch::<AlchemistSerialTasks>(value, "serial", "serial_tasks"),
+            type_mismatch::<AlchemistParallelTasks>(value, "parallel", "parallel_tasks"),
+            type_mismatch::<AlchemistShellTask>(value, "shell", "shell_script"),
+            type_mismatch::<AlchemistServiceTask>(value, "service", "service"),
+            type_mismatch::<AlchemistPipelineTask>(value, "pipeline", "pipeline"),
+        ];
+        if mismatches.iter().any(Option::is_none) {
+            return Vec::new();
+        }
+        mismatches.into_iter().flatten().collect()
+    }
+
+    /// Other names of this task, see the `aliases` field
+    pub fn aliases(&self) -> &[String] {
+        match self {
+            Self::AlchemistBasicTask(v) => v.aliases.as_deref(),
+            Self::AlchemistSerialTasks(v) => v.aliases.as_deref(),
+            Self::AlchemistParallelTasks(v) => v.aliases.as_deref(),
+            Self::AlchemistShellTask(v) => v.aliases.as_deref(),
+            Self::AlchemistServiceTask(v) => v.aliases.as_deref(),
+            Self::AlchemistPipelineTask(v) => v.aliases.as_deref(),
+        }
+        .unwrap_or_default()
+    }
+
+    /// File patterns that re-run this task in watch mode
+    pub fn watch_patterns(&self) -> Option<&Vec<String>> {
+        match self {
+            Self::AlchemistBasicTask(v) => v.watch.as_ref(),
+            Self::AlchemistSerialTasks(v) => v.watch.as_ref(),
+            Self::AlchemistParallelTasks(v) => v.watch.as_ref(),
+            Self::AlchemistShellTask(v) => v.watch.as_ref(),
+            Self::AlchemistServiceTask(v) => v.watch.as_ref(),
+            Self::AlchemistPipelineTask(v) => v.watch.as_ref(),
+        }
+    }
+
+    /// When this task runs, see the `if`, `unless` and `platform` fields
+    pub fn conditions(&self) -> Conditions<'_> {
+        macro_rules! conditions {
+            ($task:expr) => {
+                Conditions {
+                    run_if: $task.run_if.as_ref(),
+                    unless: $task.unless.as_ref(),
+                    platform: $task.platform.as_ref(),
+                }
+            };
+        }
+        match self {
+            Self::AlchemistBasicTask(v) => conditions!(v),
+            Self::AlchemistSerialTasks(v) => conditions!(v),
+            Self::AlchemistParallelTasks(v) => conditions!(v),
+            Self::AlchemistShellTask(v) => conditions!(v),
+            Self::AlchemistServiceTask(v) => conditions!(v),
+            Self::AlchemistPipelineTask(v) => conditions!(v),
+        }
+    }
+
+    /// The `matrix` of basic and shell tasks
+    pub fn matrix(&self) -> Option<&Matrix> {
+        match self {
+            Self::AlchemistBasicTask(v) => v.matrix.as_ref(),
+            Self::AlchemistShellTask(v) => v.matrix.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The instances of this task when it has a `matrix`, named like `build[target=x86_64]`
+    pub fn matrix_instances(&self, task_name: &str) -> Option<Vec<(String, AlchemistTaskType)>> {
+        let combinations = matrix::combinations(self.matrix()?);
+        let instances = combinations
+            .into_iter()
+            .map(|values| {
+                let instance = match self {
+                    Self::AlchemistBasicTask(v) => v.instance(&values).into(),
+                    Self::AlchemistShellTask(v) => v.instance(&values).into(),
+                    _ => unreachable!("only basic and shell tasks have a matrix"),
+                };
+                (matrix::instance_name(task_name, &values), instance)
+            })
+            .collect();
+        Some(instances)
+    }
+
+    /// The instance of this matrix task with `values`, in any order
+    pub fn matrix_instance(
+        &self,
+        task_name: &str,
+        values: &MatrixValues,
+    ) -> Option<AlchemistTaskType> {
+        self.matrix_instances(task_name)?
+            .into_iter()
+            .map(|(_, instance)| instance)
+            .find(|instance| matrix::same_values(instance.matrix_values(), values))
+    }
+
+    fn matrix_values(&self) -> &MatrixValues {
+        static NONE: MatrixValues = Vec::new();
+        match self {
+            Self::AlchemistBasicTask(v) => &v.matrix_values,
+            Self::AlchemistShellTask(v) => &v.matrix_values,
+            _ => &NONE,
+        }
+    }
+
+    fn runs_matrix_in_parallel(&self) -> bool {
+        match self {
+            Self::AlchemistBasicTask(v) => v.matrix_parallel,
+            Self::AlchemistShellTask(v) => v.matrix_parallel,
+            _ => None,
+        }
+        .unwrap_or(false)
+    }
+
+    /// The `finally` and `on_failure` tasks of this task
+    pub fn hooks(&self) -> Hooks<'_> {
+        macro_rules! hooks {
+            ($task:expr) => {
+                Hooks {
+                    finally: $task.finally.as_deref(),
+                    on_failure: $task.on_failure.as_deref(),
+                }
+            };
+        }
+        match self {
+            Self::AlchemistBasicTask(v) => hooks!(v),
+            Self::AlchemistSerialTasks(v) => hooks!(v),
+            Self::AlchemistParallelTasks(v) => hooks!(v),
+            Self::AlchemistShellTask(v) => hooks!(v),
+            Self::AlchemistServiceTask(v) => hooks!(v),
+            Self::AlchemistPipelineTask(v) => hooks!(v),
+        }
+    }
+
+    pub fn allows_failure(&self) -> bool {
+        match self {
+            Self::AlchemistBasicTask(v) => v.allow_failure,
+            Self::AlchemistSerialTasks(v) => v.allow_failure,
+            Self::AlchemistParallelTasks(v) => v.allow_failure,
+            Self::AlchemistShellTask(v) => v.allow_failure,
+            Self::AlchemistServiceTask(v) => v.allow_failure,
+            Self::AlchemistPipelineTask(v) => v.allow_failure,
+        }
+        .unwrap_or(false)
+    }
+
     pub fn is_shown(&self) -> bool {
         match self {
             Self::AlchemistBasicTask(v) => !v.hide.unwrap_or(false),
             Self::AlchemistSerialTasks(v) => !v.hide.unwrap_or(false),
             Self::AlchemistParallelTasks(v) => !v.hide.unwrap_or(false),
             Self::AlchemistShellTask(v) => !v.hide.unwrap_or(false),
+            Self::AlchemistServiceTask(v) => !v.hide.unwrap_or(false),
+            Self::AlchemistPipelineTask(v) => !v.hide.unwrap_or(false),
         }
     }
 }
 
+/// Tasks that run after a task, see the `finally` and `on_failure` fields
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Hooks<'a> {
+    /// Run after the task, whether it succeeded, failed or was interrupted
+    pub finally: Option<&'a [String]>,
+    /// Run after the task failed, before the `finally` tasks
+    pub on_failure: Option<&'a [String]>,
+}
+
+impl Hooks<'_> {
+    pub fn is_some(&self) -> bool {
+        self.finally.is_some() || self.on_failure.is_some()
+    }
+}
+
 impl RunnableTask for AlchemistTaskType {
     fn run<T: ToString>(&self, task_name: T, config: &AlchemistConfig) -> Result<()> {
-        match self {
-            AlchemistTaskType::AlchemistBasicTask(task) => task.run(task_name, config),
-            AlchemistTaskType::AlchemistSerialTasks(task) => task.run(task_name, config),
-            AlchemistTaskType::AlchemistParallelTasks(task) => task.run(task_name, config),
-            AlchemistTaskType::AlchemistShellTask(task) => task.run(task_name, config),
+        let task_name = task_name.to_string();
+        if let Some(reason) = self.conditions().skip_reason(config.project_dir.as_deref()) {
+            terminal::info(format!("Skipping task {task_name}: {reason}"));
+            report::skip(&task_name, &self.describe().task_type);
+            return Ok(());
         }
+        let hooks = self.hooks();
+        let _pending_cleanup = hooks.is_some().then(process::PendingCleanup::new);
+        let record = report::start(&task_name, &self.describe().task_type);
+        let result = match self.matrix_instances(&task_name) {
+            Some(instances) => run_matrix(
+                &task_name,
+                instances,
+                self.runs_matrix_in_parallel(),
+                config,
+            ),
+            None => match self {
+                AlchemistTaskType::AlchemistBasicTask(task) => task.run(&task_name, config),
+                AlchemistTaskType::AlchemistSerialTasks(task) => task.run(&task_name, config),
+                AlchemistTaskType::AlchemistParallelTasks(task) => task.run(&task_name, config),
+                AlchemistTaskType::AlchemistShellTask(task) => task.run(&task_name, config),
+                AlchemistTaskType::AlchemistServiceTask(task) => task.run(&task_name, config),
+                AlchemistTaskType::AlchemistPipelineTask(task) => task.run(&task_name, config),
+            },
+        };
+        let result = match report::finish(record, &result, self.allows_failure()) {
+            TaskStatus::AllowedFailure => {
+                if let Err(e) = result {
+                    terminal::warn(format!(
+                        "Task {task_name} failed, continuing as its failure is allowed: {}",
+                        e.message()
+                    ));
+                }
+                Ok(())
+            }
+            _ => result,
+        };
+        if !hooks.is_some() {
+            return result;
+        }
+        process::cleanup(|| {
+            if result.is_err()
+                && let Some(on_failure) = hooks.on_failure
+                && let Err(e) = run_hooks("on_failure", &task_name, on_failure, config)
+            {
+                terminal::error(e);
+            }
+            match hooks
+                .finally
+                .map(|finally| run_hooks("finally", &task_name, finally, config))
+            {
+                Some(Err(e)) if result.is_err() => {
+                    terminal::error(e);
+                    result
+                }
+                Some(Err(e)) => Err(e),
+                _ => result,
+            }
+        })
     }
 
     fn describe(&self) -> TaskDescription {
@@ -336,6 +1460,8 @@ impl RunnableTask for AlchemistTaskType {
             AlchemistTaskType::AlchemistSerialTasks(task) => task.describe(),
             AlchemistTaskType::AlchemistParallelTasks(task) => task.describe(),
             AlchemistTaskType::AlchemistShellTask(task) => task.describe(),
+            AlchemistTaskType::AlchemistServiceTask(task) => task.describe(),
+            AlchemistTaskType::AlchemistPipelineTask(task) => task.describe(),
         }
     }
 }
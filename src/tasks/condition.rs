@@ -0,0 +1,110 @@
+//! Conditions deciding whether a task runs, see the `if`, `unless` and
+//! `platform` task fields
+//!
+//! A condition is one of these expressions, or else a command run in `sh`
+//! that is true when it exits successfully:
+//! - `env:NAME` the environment variable `NAME` is set and not empty
+//! - `env:NAME=value` the environment variable `NAME` equals `value`
+//! - `exists:path` the file or directory exists (relative to the project)
+//! - `os:name` the operating system is `name` (e.g. `linux`, `macos`)
+//! - `arch:name` the CPU architecture is `name` (e.g. `x86_64`, `aarch64`)
+
+#[cfg(test)]
+#[path = "condition_test.rs"]
+mod condition_test;
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The conditions of a single task
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Conditions<'a> {
+    pub run_if: Option<&'a String>,
+    pub unless: Option<&'a String>,
+    pub platform: Option<&'a Vec<String>>,
+}
+
+/// Whether `platform` names the current operating system, its family
+/// (`unix`), the architecture or both as `os-arch`
+fn is_current_platform(platform: &str) -> bool {
+    [
+        std::env::consts::OS.to_string(),
+        std::env::consts::FAMILY.to_string(),
+        std::env::consts::ARCH.to_string(),
+        format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+    ]
+    .iter()
+    .any(|p| p.eq_ignore_ascii_case(platform.trim()))
+}
+
+/// Evaluates the condition `expression` for a task of the project in `project_dir`
+pub fn evaluate(expression: &str, project_dir: Option<&Path>) -> bool {
+    evaluate_with_env(expression, project_dir, |name| std::env::var_os(name))
+}
+
+/// Like [`evaluate`], looking up environment variables with `env`
+fn evaluate_with_env(
+    expression: &str,
+    project_dir: Option<&Path>,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> bool {
+    let expression = expression.trim();
+    if let Some(variable) = expression.strip_prefix("env:") {
+        return match variable.split_once('=') {
+            Some((name, value)) => env(name).is_some_and(|v| v == value),
+            None => env(variable).is_some_and(|v| !v.is_empty()),
+        };
+    }
+    if let Some(path) = expression.strip_prefix("exists:") {
+        return match project_dir {
+            Some(project_dir) => project_dir.join(path).exists(),
+            None => Path::new(path).exists(),
+        };
+    }
+    if let Some(os) = expression.strip_prefix("os:") {
+        return os.trim().eq_ignore_ascii_case(std::env::consts::OS);
+    }
+    if let Some(arch) = expression.strip_prefix("arch:") {
+        return arch.trim().eq_ignore_ascii_case(std::env::consts::ARCH);
+    }
+
+    let mut cmd = Command::new("sh");
+    if let Some(project_dir) = project_dir {
+        cmd.current_dir(project_dir);
+    }
+    cmd.arg("-c")
+        .arg(expression)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+impl Conditions<'_> {
+    /// Why the task should not run, `None` when it should
+    pub fn skip_reason(&self, project_dir: Option<&Path>) -> Option<String> {
+        if let Some(platform) = self.platform
+            && !platform.iter().any(|p| is_current_platform(p))
+        {
+            return Some(format!(
+                "only runs on {}, not {}-{}",
+                platform.join(", "),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ));
+        }
+        if let Some(run_if) = self.run_if
+            && !evaluate(run_if, project_dir)
+        {
+            return Some(format!("`if = \"{run_if}\"` is false"));
+        }
+        if let Some(unless) = self.unless
+            && evaluate(unless, project_dir)
+        {
+            return Some(format!("`unless = \"{unless}\"` is true"));
+        }
+        None
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn env_conditions() {
+    let env = |name: &str| match name {
+        "TEST" => Some(OsString::from("yes")),
+        "EMPTY" => Some(OsString::new()),
+        _ => None,
+    };
+    assert!(evaluate_with_env("env:TEST", None, env));
+    assert!(evaluate_with_env("env:TEST=yes", None, env));
+    assert!(!evaluate_with_env("env:TEST=no", None, env));
+    assert!(!evaluate_with_env("env:EMPTY", None, env));
+    assert!(!evaluate_with_env("env:MISSING", None, env));
+    assert!(evaluate("env:PATH", None));
+}
+
+#[test]
+fn exists_conditions_are_relative_to_the_project() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(tmpdir.path().join("Dockerfile"), "").unwrap();
+    assert!(evaluate("exists:Dockerfile", Some(tmpdir.path())));
+    assert!(!evaluate("exists:Containerfile", Some(tmpdir.path())));
+}
+
+#[test]
+fn platform_conditions() {
+    assert!(evaluate(&format!("os:{}", std::env::consts::OS), None));
+    assert!(!evaluate("os:plan9", None));
+    assert!(evaluate(&format!("arch:{}", std::env::consts::ARCH), None));
+}
+
+#[test]
+fn command_conditions() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    assert!(evaluate("true", None));
+    assert!(!evaluate("false", None));
+    assert!(evaluate(
+        "test \"$(pwd -P)\" = \"$(cd . && pwd -P)\"",
+        Some(tmpdir.path())
+    ));
+}
+
+#[test]
+fn skip_reasons() {
+    let run_if = "false".to_string();
+    let unless = "true".to_string();
+    let platform = vec!["plan9".to_string()];
+    let current = vec![std::env::consts::OS.to_string()];
+
+    assert_eq!(Conditions::default().skip_reason(None), None);
+    assert_eq!(
+        Conditions {
+            run_if: Some(&run_if),
+            ..Default::default()
+        }
+        .skip_reason(None),
+        Some("`if = \"false\"` is false".to_string())
+    );
+    assert_eq!(
+        Conditions {
+            unless: Some(&unless),
+            ..Default::default()
+        }
+        .skip_reason(None),
+        Some("`unless = \"true\"` is true".to_string())
+    );
+    assert!(
+        Conditions {
+            platform: Some(&platform),
+            ..Default::default()
+        }
+        .skip_reason(None)
+        .unwrap()
+        .starts_with("only runs on plan9")
+    );
+    assert_eq!(
+        Conditions {
+            platform: Some(&current),
+            ..Default::default()
+        }
+        .skip_reason(None),
+        None
+    );
+}
@@ -0,0 +1,106 @@
+//! Expands a task with a `matrix` into one instance per combination of values
+//!
+//! An instance is named after its values, e.g. `build[target=x86_64,profile=dev]`,
+//! and `${target}` in its fields is replaced by the value of `target`.
+
+#[cfg(test)]
+#[path = "matrix_test.rs"]
+mod matrix_test;
+
+use indexmap::IndexMap;
+
+use super::variables;
+
+/// The values of each axis of a matrix, e.g. `{ target = ["x86_64", "aarch64"] }`
+pub type Matrix = IndexMap<String, Vec<String>>;
+
+/// The value of every axis for one instance of a matrix
+pub type MatrixValues = Vec<(String, String)>;
+
+/// Characters a value can not contain, as they separate the values in instance names
+const RESERVED: [char; 4] = [',', '=', '[', ']'];
+
+/// Makes sure every instance of the matrix of `task_name` can be named and run
+/// by its name, describing the problem otherwise
+pub fn check(task_name: &str, matrix: &Matrix) -> Result<(), String> {
+    if matrix.is_empty() {
+        return Err(format!("Matrix task '{task_name}' has no axes."));
+    }
+    for (axis, values) in matrix {
+        if !variables::is_valid_name(axis) {
+            return Err(format!(
+                "Matrix task '{task_name}' has an axis '{axis}', which is not a valid variable name."
+            ));
+        }
+        if values.is_empty() {
+            return Err(format!(
+                "The axis '{axis}' of matrix task '{task_name}' has no values."
+            ));
+        }
+        if let Some(value) = values
+            .iter()
+            .find(|value| value.contains(RESERVED) || value.trim() != value.as_str())
+        {
+            return Err(format!(
+                "The value '{value}' of axis '{axis}' of matrix task '{task_name}' can not contain ',', '=', '[' or ']', or start or end with whitespace."
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// All combinations of the values of `matrix`, the first axis changing slowest
+pub fn combinations(matrix: &Matrix) -> Vec<MatrixValues> {
+    let mut combinations = vec![Vec::new()];
+    for (axis, values) in matrix {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination: MatrixValues| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((axis.clone(), value.clone()));
+                    combination
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// The name of the instance of `task_name` with `values`, like `build[target=x86_64]`
+pub fn instance_name(task_name: &str, values: &MatrixValues) -> String {
+    let values = values
+        .iter()
+        .map(|(axis, value)| format!("{axis}={value}"))
+        .collect::<Vec<_>>();
+    format!("{task_name}[{}]", values.join(","))
+}
+
+/// Splits an instance name like `build[target=x86_64]` into the task name and its values
+pub fn parse_instance_name(name: &str) -> Option<(&str, MatrixValues)> {
+    let (task_name, values) = name.strip_suffix(']')?.split_once('[')?;
+    let values = values
+        .split(',')
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(axis, value)| (axis.trim().to_string(), value.trim().to_string()))
+        })
+        .collect::<Option<MatrixValues>>()?;
+    Some((task_name, values))
+}
+
+/// Whether `a` and `b` hold the same values, in any order
+pub fn same_values(a: &MatrixValues, b: &MatrixValues) -> bool {
+    let mut a = a.clone();
+    let mut b = b.clone();
+    a.sort();
+    b.sort();
+    a == b
+}
+
+/// Replaces `${axis}` in `text` with the value of `axis`
+pub fn interpolate(text: &str, values: &MatrixValues) -> String {
+    values.iter().fold(text.to_string(), |text, (axis, value)| {
+        text.replace(&format!("${{{axis}}}"), value)
+    })
+}
@@ -0,0 +1,77 @@
+use super::*;
+
+fn values(pairs: &[(&str, &str)]) -> MatrixValues {
+    pairs
+        .iter()
+        .map(|(axis, value)| (axis.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn combinations_of_all_axes() {
+    let mut matrix = Matrix::new();
+    matrix.insert(
+        "target".to_string(),
+        vec!["x86_64".to_string(), "aarch64".to_string()],
+    );
+    matrix.insert(
+        "profile".to_string(),
+        vec!["dev".to_string(), "release".to_string()],
+    );
+
+    let names = combinations(&matrix)
+        .iter()
+        .map(|values| instance_name("build", values))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "build[target=x86_64,profile=dev]",
+            "build[target=x86_64,profile=release]",
+            "build[target=aarch64,profile=dev]",
+            "build[target=aarch64,profile=release]",
+        ]
+    );
+}
+
+#[test]
+fn instance_names_are_parsed() {
+    let (task_name, parsed) = parse_instance_name("build[profile=dev, target=x86_64]").unwrap();
+    assert_eq!(task_name, "build");
+    assert!(same_values(
+        &parsed,
+        &values(&[("target", "x86_64"), ("profile", "dev")])
+    ));
+
+    assert!(parse_instance_name("build").is_none());
+    assert!(parse_instance_name("build[dev]").is_none());
+}
+
+#[test]
+fn values_are_interpolated() {
+    let values = values(&[("target", "x86_64"), ("profile", "dev")]);
+    assert_eq!(
+        interpolate("--target=${target} --${profile} ${other}", &values),
+        "--target=x86_64 --dev ${other}"
+    );
+}
+
+#[test]
+fn matrices_are_checked() {
+    let matrix = |axis: &str, values: &[&str]| {
+        Matrix::from([(
+            axis.to_string(),
+            values.iter().map(|v| v.to_string()).collect(),
+        )])
+    };
+    assert!(check("build", &matrix("target", &["x86_64", "aarch64"])).is_ok());
+    assert!(check("build", &Matrix::new()).is_err());
+    assert!(check("build", &matrix("target", &[])).is_err());
+    assert!(check("build", &matrix("my-target", &["x86_64"])).is_err());
+    for value in ["a,b", "a=b", "a]", "[a", " a"] {
+        assert!(
+            check("build", &matrix("target", &[value])).is_err(),
+            "{value}"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Variables captured from the output of tasks, see the `capture` task field
+//!
+//! A captured variable is set in the environment of every command started
+//! after it, and `${NAME}` in the command, args and env of basic and service
+//! tasks is replaced by its value.
+
+#[cfg(test)]
+#[path = "variables_test.rs"]
+mod variables_test;
+
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use regex::{Captures, Regex};
+
+use crate::cli::terminal::Stream;
+
+static VARIABLES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+static REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex"));
+
+fn variables() -> std::sync::MutexGuard<'static, BTreeMap<String, String>> {
+    VARIABLES.lock().expect("variables poisoned")
+}
+
+/// Whether `name` can be used as a variable, like `GIT_SHA`
+pub fn is_valid_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn set<S: ToString>(name: S, value: S) {
+    variables().insert(name.to_string(), value.to_string());
+}
+
+/// Replaces `${NAME}` in `text` with the value of the variable `NAME`,
+/// references to unknown variables are kept as they are
+pub fn substitute(text: &str) -> String {
+    let variables = variables();
+    REFERENCE
+        .replace_all(text, |reference: &Captures| {
+            variables
+                .get(&reference[1])
+                .cloned()
+                .unwrap_or_else(|| reference[0].to_string())
+        })
+        .into_owned()
+}
+
+/// Sets every captured variable in the environment of `cmd`
+pub fn export(cmd: &mut Command) {
+    cmd.envs(variables().iter());
+}
+
+/// Collects the stdout of a process to capture it into a variable
+#[derive(Debug, Default, Clone)]
+pub struct Capture(Arc<Mutex<String>>);
+
+impl Capture {
+    pub fn add_line(&self, stream: Stream, line: &str) {
+        if stream == Stream::Stdout {
+            let mut output = self.0.lock().expect("capture poisoned");
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    /// The collected output without surrounding whitespace
+    pub fn value(&self) -> String {
+        self.0.lock().expect("capture poisoned").trim().to_string()
+    }
+}
@@ -0,0 +1,28 @@
+use super::*;
+
+#[test]
+fn substitutes_known_variables() {
+    set("ALCHEMIST_VARIABLES_VERSION", "1.2.3");
+    assert_eq!(
+        substitute("v${ALCHEMIST_VARIABLES_VERSION}-${ALCHEMIST_VARIABLES_MISSING} $HOME"),
+        "v1.2.3-${ALCHEMIST_VARIABLES_MISSING} $HOME"
+    );
+}
+
+#[test]
+fn capture_keeps_trimmed_stdout() {
+    let capture = Capture::default();
+    capture.add_line(Stream::Stdout, "  abc123");
+    capture.add_line(Stream::Stderr, "warning");
+    capture.add_line(Stream::Stdout, "");
+    assert_eq!(capture.value(), "abc123");
+}
+
+#[test]
+fn variable_names() {
+    assert!(is_valid_name("GIT_SHA"));
+    assert!(is_valid_name("_version2"));
+    assert!(!is_valid_name("2FAST"));
+    assert!(!is_valid_name("git-sha"));
+    assert!(!is_valid_name(""));
+}
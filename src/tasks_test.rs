@@ -13,15 +13,9 @@ fn basic_task() {
     let basic = AlchemistBasicTask {
         command: "sh".to_string(),
         args: Some(vec!["-c".to_string(), "true".to_string()]),
-        env: None,
-        hide: None,
+        ..Default::default()
     };
-    let ret = basic.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = basic.run("name", &AlchemistConfig::default());
     assert_eq!(ret, Result::Ok(()));
 }
 
@@ -39,14 +33,9 @@ fn basic_task_env_vars() {
             "ALCHEMIST_TASKS_TEST_VAR".to_string(),
             "VAR_VALUE".to_string(),
         )])),
-        hide: None,
+        ..Default::default()
     };
-    let ret = basic.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = basic.run("name", &AlchemistConfig::default());
 
     assert!(ret.is_ok());
     assert!(tmpfile.exists());
@@ -60,15 +49,9 @@ fn basic_task_nonzero_exit_code() {
     let basic = AlchemistBasicTask {
         command: "sh".to_string(),
         args: Some(vec!["-c".to_string(), "false".to_string()]),
-        env: None,
-        hide: None,
+        ..Default::default()
     };
-    let ret = basic.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = basic.run("name", &AlchemistConfig::default());
     assert_eq!(
         ret,
         Result::Err(AlchemistError::AssertionErrorVariant(ErrorContext(
@@ -86,15 +69,9 @@ fn basic_task_spawn_failure() {
     let basic = AlchemistBasicTask {
         command: "/etc/passwd".to_string(),
         args: None,
-        env: None,
-        hide: None,
+        ..Default::default()
     };
-    let ret = basic.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = basic.run("name", &AlchemistConfig::default());
     let alchem_err = ret.as_ref().err().unwrap();
     let kind = match alchem_err {
         AlchemistError::IOErrorVariant(ErrorContext(v, _s)) => Some(v.kind()),
@@ -117,14 +94,9 @@ fn basic_task_spawn_failure() {
 fn shell_task() {
     let shell = AlchemistShellTask {
         shell_script: "true".to_string(),
-        hide: None,
+        ..Default::default()
     };
-    let ret = shell.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = shell.run("name", &AlchemistConfig::default());
     assert_eq!(ret, Result::Ok(()));
 }
 
@@ -132,14 +104,9 @@ fn shell_task() {
 fn shell_task_nonzero_exit_code() {
     let shell = AlchemistShellTask {
         shell_script: "false".to_string(),
-        hide: None,
+        ..Default::default()
     };
-    let ret = shell.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = shell.run("name", &AlchemistConfig::default());
     assert_eq!(
         ret,
         Result::Err(AlchemistError::AssertionErrorVariant(ErrorContext(
@@ -149,6 +116,71 @@ fn shell_task_nonzero_exit_code() {
     );
 }
 
+#[test]
+fn shell_task_ok_exit_codes() {
+    let shell = AlchemistShellTask {
+        shell_script: "exit 1".to_string(),
+        ok_exit_codes: Some(vec![0, 1]),
+        ..Default::default()
+    };
+    assert_eq!(
+        shell.run("name", &AlchemistConfig::default()),
+        Result::Ok(())
+    );
+
+    let shell = AlchemistShellTask {
+        shell_script: "true".to_string(),
+        ok_exit_codes: Some(vec![1]),
+        ..Default::default()
+    };
+    assert!(shell.run("name", &AlchemistConfig::default()).is_err());
+}
+
+#[test]
+fn shell_task_log_file() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let shell = AlchemistShellTask {
+        shell_script: "echo to stdout; echo to stderr >&2".to_string(),
+        log_file: Some("logs/shell.log".to_string()),
+        ..Default::default()
+    };
+    let ret = shell.run(
+        "name",
+        &AlchemistConfig {
+            project_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(ret, Result::Ok(()));
+
+    let log = std::fs::read_to_string(tmpdir.path().join("logs/shell.log")).unwrap();
+    assert!(log.contains("to stdout\n"));
+    assert!(log.contains("to stderr\n"));
+}
+
+#[test]
+fn shell_task_skipped_by_condition() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let shell: AlchemistTaskType = AlchemistShellTask {
+        shell_script: "touch ran".to_string(),
+        unless: Some("exists:skip".to_string()),
+        ..Default::default()
+    }
+    .into();
+    let config = AlchemistConfig {
+        project_dir: Some(tmpdir.path().to_path_buf()),
+        ..Default::default()
+    };
+
+    std::fs::write(tmpdir.path().join("skip"), "").unwrap();
+    assert_eq!(shell.run("name", &config), Result::Ok(()));
+    assert!(!tmpdir.path().join("ran").exists());
+
+    std::fs::remove_file(tmpdir.path().join("skip")).unwrap();
+    assert_eq!(shell.run("name", &config), Result::Ok(()));
+    assert!(tmpdir.path().join("ran").exists());
+}
+
 //
 // SerialTask tests:
 //
@@ -157,14 +189,9 @@ fn shell_task_nonzero_exit_code() {
 fn serial_task_empty() {
     let serial = AlchemistSerialTasks {
         serial_tasks: Vec::new(),
-        hide: None,
+        ..Default::default()
     };
-    let ret = serial.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = serial.run("name", &AlchemistConfig::default());
     assert!(ret.is_ok());
 }
 
@@ -174,7 +201,7 @@ fn serial_tasks_ok() {
     let tmpfile = tmpdir.path().join("output.txt");
     let serial = AlchemistSerialTasks {
         serial_tasks: vec!["one".to_string(), "two".to_string()],
-        hide: None,
+        ..Default::default()
     };
     let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
 
@@ -186,8 +213,7 @@ fn serial_tasks_ok() {
                 "-c".to_string(),
                 format!("echo one >> {}", &tmpfile.display()).to_string(),
             ]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
@@ -199,13 +225,18 @@ fn serial_tasks_ok() {
                 "-c".to_string(),
                 format!("echo two >> {}", &tmpfile.display()).to_string(),
             ]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
 
-    let ret = serial.run("name", &AlchemistConfig { tasks: tasks });
+    let ret = serial.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            ..Default::default()
+        },
+    );
     assert!(ret.is_ok());
     assert!(tmpfile.exists());
     let tmpdata = std::fs::read(tmpfile).unwrap();
@@ -217,7 +248,7 @@ fn serial_tasks_ok() {
 fn test_serial_task_one_fail() {
     let serial = AlchemistSerialTasks {
         serial_tasks: vec!["one".to_string(), "two".to_string()],
-        hide: None,
+        ..Default::default()
     };
     let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
     tasks.insert(
@@ -225,8 +256,7 @@ fn test_serial_task_one_fail() {
         AlchemistBasicTask {
             command: "sh".to_string(),
             args: Some(vec!["-c".to_string(), "true".to_string()]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
@@ -235,12 +265,17 @@ fn test_serial_task_one_fail() {
         AlchemistBasicTask {
             command: "sh".to_string(),
             args: Some(vec!["-c".to_string(), "false".to_string()]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
-    let ret = serial.run("name", &AlchemistConfig { tasks: tasks });
+    let ret = serial.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            ..Default::default()
+        },
+    );
     assert_eq!(
         ret,
         Result::Err(AlchemistError::AssertionErrorVariant(ErrorContext(
@@ -253,6 +288,198 @@ fn test_serial_task_one_fail() {
     );
 }
 
+#[test]
+fn serial_task_continues_after_allowed_failure() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let serial = AlchemistSerialTasks {
+        serial_tasks: vec!["lint".to_string(), "build".to_string()],
+        ..Default::default()
+    };
+    let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
+    tasks.insert(
+        "lint".to_string(),
+        AlchemistShellTask {
+            shell_script: "exit 3".to_string(),
+            allow_failure: Some(true),
+            ..Default::default()
+        }
+        .into(),
+    );
+    tasks.insert(
+        "build".to_string(),
+        AlchemistShellTask {
+            shell_script: "touch built".to_string(),
+            ..Default::default()
+        }
+        .into(),
+    );
+    let ret = serial.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            project_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(ret, Result::Ok(()));
+    assert!(tmpdir.path().join("built").exists());
+}
+
+#[test]
+fn captured_output_is_used_by_later_tasks() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let serial = AlchemistSerialTasks {
+        serial_tasks: vec![
+            "version".to_string(),
+            "build".to_string(),
+            "tag".to_string(),
+        ],
+        ..Default::default()
+    };
+    let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
+    tasks.insert(
+        "version".to_string(),
+        AlchemistShellTask {
+            shell_script: "echo ' 1.4.2 '; echo noise >&2".to_string(),
+            capture: Some("ALCHEMIST_TEST_VERSION".to_string()),
+            ..Default::default()
+        }
+        .into(),
+    );
+    tasks.insert(
+        "build".to_string(),
+        AlchemistBasicTask {
+            command: "touch".to_string(),
+            args: Some(vec!["build-${ALCHEMIST_TEST_VERSION}".to_string()]),
+            ..Default::default()
+        }
+        .into(),
+    );
+    tasks.insert(
+        "tag".to_string(),
+        AlchemistShellTask {
+            shell_script: "touch \"tag-$ALCHEMIST_TEST_VERSION\"".to_string(),
+            ..Default::default()
+        }
+        .into(),
+    );
+    let ret = serial.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            project_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(ret, Result::Ok(()));
+    assert!(tmpdir.path().join("build-1.4.2").exists());
+    assert!(tmpdir.path().join("tag-1.4.2").exists());
+}
+
+fn hook_tasks(project_dir: &std::path::Path) -> AlchemistConfig {
+    let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
+    for (name, script) in [
+        ("fail", "exit 1"),
+        ("notify", "echo notified >> hooks"),
+        ("teardown", "echo teardown >> hooks"),
+    ] {
+        tasks.insert(
+            name.to_string(),
+            AlchemistShellTask {
+                shell_script: script.to_string(),
+                ..Default::default()
+            }
+            .into(),
+        );
+    }
+    AlchemistConfig {
+        tasks,
+        project_dir: Some(project_dir.to_path_buf()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn serial_task_hooks_after_failure() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let serial: AlchemistTaskType = AlchemistSerialTasks {
+        serial_tasks: vec!["fail".to_string()],
+        finally: Some(vec!["teardown".to_string()]),
+        on_failure: Some(vec!["notify".to_string()]),
+        ..Default::default()
+    }
+    .into();
+    let ret = serial.run("name", &hook_tasks(tmpdir.path()));
+    assert!(ret.is_err());
+    assert_eq!(
+        std::fs::read_to_string(tmpdir.path().join("hooks")).unwrap(),
+        "notified\nteardown\n"
+    );
+}
+
+#[test]
+fn serial_task_hooks_after_success() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let serial: AlchemistTaskType = AlchemistSerialTasks {
+        serial_tasks: Vec::new(),
+        finally: Some(vec!["teardown".to_string()]),
+        on_failure: Some(vec!["notify".to_string()]),
+        ..Default::default()
+    }
+    .into();
+    let ret = serial.run("name", &hook_tasks(tmpdir.path()));
+    assert_eq!(ret, Result::Ok(()));
+    assert_eq!(
+        std::fs::read_to_string(tmpdir.path().join("hooks")).unwrap(),
+        "teardown\n"
+    );
+}
+
+#[test]
+fn failing_finally_task_fails_the_task() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let serial: AlchemistTaskType = AlchemistSerialTasks {
+        serial_tasks: Vec::new(),
+        finally: Some(vec!["fail".to_string(), "teardown".to_string()]),
+        ..Default::default()
+    }
+    .into();
+    let ret = serial.run("name", &hook_tasks(tmpdir.path()));
+    assert!(ret.is_err());
+    // The remaining finally tasks still run
+    assert!(tmpdir.path().join("hooks").exists());
+}
+
+#[test]
+fn serial_tasks_cross_project_reference() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let project_dir = tmpdir.path().join("services").join("api");
+    std::fs::create_dir_all(&project_dir).unwrap();
+    std::fs::write(
+        project_dir.join("alchemist.toml"),
+        "[tasks.where]\nshell_script = \"pwd > output.txt\"\n",
+    )
+    .unwrap();
+
+    let serial = AlchemistSerialTasks {
+        serial_tasks: vec!["services/api:where".to_string()],
+        ..Default::default()
+    };
+    let ret = serial.run(
+        "name",
+        &AlchemistConfig {
+            project_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        },
+    );
+    assert!(ret.is_ok());
+    let output = std::fs::read_to_string(project_dir.join("output.txt")).unwrap();
+    assert_eq!(
+        std::fs::canonicalize(output.trim()).unwrap(),
+        std::fs::canonicalize(&project_dir).unwrap()
+    );
+}
+
 //
 // ParallelTasks tests:
 //
@@ -261,14 +488,9 @@ fn test_serial_task_one_fail() {
 fn parallel_task_empty() {
     let parallel = AlchemistParallelTasks {
         parallel_tasks: Vec::new(),
-        hide: None,
+        ..Default::default()
     };
-    let ret = parallel.run(
-        "name",
-        &AlchemistConfig {
-            tasks: IndexMap::new(),
-        },
-    );
+    let ret = parallel.run("name", &AlchemistConfig::default());
     assert!(ret.is_ok());
 }
 
@@ -276,7 +498,7 @@ fn parallel_task_empty() {
 fn parallel_tasks_one_fail() {
     let parallel = AlchemistParallelTasks {
         parallel_tasks: vec!["one".to_string(), "two".to_string()],
-        hide: None,
+        ..Default::default()
     };
     let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
     tasks.insert(
@@ -284,8 +506,7 @@ fn parallel_tasks_one_fail() {
         AlchemistBasicTask {
             command: "sh".to_string(),
             args: Some(vec!["-c".to_string(), "true".to_string()]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
@@ -294,12 +515,17 @@ fn parallel_tasks_one_fail() {
         AlchemistBasicTask {
             command: "sh".to_string(),
             args: Some(vec!["-c".to_string(), "false".to_string()]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
-    let ret = parallel.run("name", &AlchemistConfig { tasks: tasks });
+    let ret = parallel.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            ..Default::default()
+        },
+    );
     assert_eq!(
         ret,
         Result::Err(AlchemistError::AssertionErrorVariant(ErrorContext(
@@ -315,7 +541,7 @@ fn parallel_tasks_actually_run_parallel() {
     let tmpfile = tmpdir.path().join("output.txt");
     let parallel = AlchemistParallelTasks {
         parallel_tasks: vec!["one".to_string(), "two".to_string()],
-        hide: None,
+        ..Default::default()
     };
     let mut tasks: IndexMap<String, AlchemistTaskType> = IndexMap::new();
 
@@ -327,8 +553,7 @@ fn parallel_tasks_actually_run_parallel() {
                 "-c".to_string(),
                 format!("sleep 0.2; echo one >> {}", &tmpfile.display()).to_string(),
             ]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
@@ -340,16 +565,352 @@ fn parallel_tasks_actually_run_parallel() {
                 "-c".to_string(),
                 format!("echo two >> {}", &tmpfile.display()).to_string(),
             ]),
-            env: None,
-            hide: None,
+            ..Default::default()
         }
         .into(),
     );
 
-    let ret = parallel.run("name", &AlchemistConfig { tasks: tasks });
+    let ret = parallel.run(
+        "name",
+        &AlchemistConfig {
+            tasks,
+            ..Default::default()
+        },
+    );
     assert!(ret.is_ok());
     assert!(tmpfile.exists());
     let tmpdata = std::fs::read(tmpfile).unwrap();
     let output = std::str::from_utf8(&tmpdata).unwrap();
     assert_eq!("two\none\n", output);
 }
+
+//
+// ServiceTask tests:
+//
+
+#[test]
+fn service_task_waits_until_ready() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let ready_file = tmpdir.path().join("ready");
+    let service = AlchemistServiceTask {
+        service: "sh".to_string(),
+        args: Some(vec![
+            "-c".to_string(),
+            format!("sleep 0.3; touch {}; sleep 30", ready_file.display()),
+        ]),
+        ready: Some(AlchemistReadinessCheck {
+            command: Some(format!("test -f {}", ready_file.display())),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let ret = service.run("server", &AlchemistConfig::default());
+    assert!(ret.is_ok());
+    assert!(ready_file.exists());
+    process::stop_services();
+}
+
+#[test]
+fn service_task_ready_on_log_line() {
+    let service = AlchemistServiceTask {
+        service: "sh".to_string(),
+        args: Some(vec![
+            "-c".to_string(),
+            "echo starting; echo listening on 8080; sleep 30".to_string(),
+        ]),
+        ready: Some(AlchemistReadinessCheck {
+            log: Some("listening on \\d+".to_string()),
+            timeout: Some(5),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert!(service.run("server", &AlchemistConfig::default()).is_ok());
+    process::stop_services();
+}
+
+#[test]
+fn service_task_exits_before_ready() {
+    let service = AlchemistServiceTask {
+        service: "sh".to_string(),
+        args: Some(vec!["-c".to_string(), "exit 1".to_string()]),
+        ready: Some(AlchemistReadinessCheck {
+            command: Some("false".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let ret = service.run("server", &AlchemistConfig::default());
+    assert!(ret.is_err_and(|e| e.to_string().contains("exited")));
+}
+
+#[test]
+fn service_task_not_ready_in_time() {
+    let service = AlchemistServiceTask {
+        service: "sleep".to_string(),
+        args: Some(vec!["30".to_string()]),
+        ready: Some(AlchemistReadinessCheck {
+            command: Some("false".to_string()),
+            timeout: Some(1),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let ret = service.run("server", &AlchemistConfig::default());
+    assert!(ret.is_err_and(|e| e.to_string().contains("not ready within 1 seconds")));
+}
+
+//
+// PipelineTask tests:
+//
+
+fn pipeline_config(
+    project_dir: &std::path::Path,
+    stages: Vec<(&str, AlchemistTaskType)>,
+) -> AlchemistConfig {
+    AlchemistConfig {
+        tasks: stages
+            .into_iter()
+            .map(|(name, task)| (name.to_string(), task))
+            .collect(),
+        project_dir: Some(project_dir.to_path_buf()),
+        ..Default::default()
+    }
+}
+
+fn shell(script: &str) -> AlchemistTaskType {
+    AlchemistShellTask {
+        shell_script: script.to_string(),
+        ..Default::default()
+    }
+    .into()
+}
+
+fn pipeline(stages: &[&str]) -> AlchemistPipelineTask {
+    AlchemistPipelineTask {
+        pipeline: stages.iter().map(|s| s.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pipeline_task_pipes_stages() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(
+        tmpdir.path(),
+        vec![
+            ("gen", shell("printf 'b\\na\\nc\\n'")),
+            ("filter", shell("grep -v c")),
+            (
+                "sort",
+                AlchemistBasicTask {
+                    command: "sort".to_string(),
+                    stdout: Some("out/sorted.txt".to_string()),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ],
+    );
+    let ret = pipeline(&["gen", "filter", "sort"]).run("name", &config);
+    assert_eq!(ret, Result::Ok(()));
+    assert_eq!(
+        std::fs::read_to_string(tmpdir.path().join("out/sorted.txt")).unwrap(),
+        "a\nb\n"
+    );
+}
+
+#[test]
+fn basic_task_redirects_stdin_and_stdout() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(tmpdir.path().join("in.txt"), "hello\n").unwrap();
+    let task = AlchemistBasicTask {
+        command: "cat".to_string(),
+        stdin: Some("in.txt".to_string()),
+        stdout: Some("out.txt".to_string()),
+        ..Default::default()
+    };
+    let ret = task.run("name", &pipeline_config(tmpdir.path(), Vec::new()));
+    assert_eq!(ret, Result::Ok(()));
+    assert_eq!(
+        std::fs::read_to_string(tmpdir.path().join("out.txt")).unwrap(),
+        "hello\n"
+    );
+}
+
+#[test]
+fn pipeline_task_fails_when_any_stage_fails() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(
+        tmpdir.path(),
+        vec![
+            ("gen", shell("echo data; exit 3")),
+            ("upload", shell("cat")),
+        ],
+    );
+    let ret = pipeline(&["gen", "upload"]).run("name", &config);
+    assert!(ret.is_err_and(|e| {
+        e.to_string()
+            .contains("Pipeline 'name' failed at stage 'gen' (exit code 3).")
+    }));
+}
+
+#[test]
+fn pipeline_task_does_not_hang_when_a_stage_stops_reading() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(
+        tmpdir.path(),
+        vec![("gen", shell("yes")), ("first", shell("head -n 1"))],
+    );
+    let ret = pipeline(&["gen", "first"]).run("name", &config);
+    // `yes` is stopped by SIGPIPE, which fails the pipeline like `pipefail`
+    assert!(ret.is_err_and(|e| e.to_string().contains("failed at stage 'gen'")));
+}
+
+#[test]
+fn pipeline_task_rejects_other_task_types() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(
+        tmpdir.path(),
+        vec![
+            ("gen", shell("touch started")),
+            ("steps", AlchemistSerialTasks::default().into()),
+        ],
+    );
+    let ret = pipeline(&["gen", "steps"]).run("name", &config);
+    assert!(ret.is_err_and(|e| {
+        e.to_string()
+            .contains("only command and shell tasks can be piped")
+    }));
+
+    let ret = pipeline(&["gen", "missing"]).run("name", &config);
+    assert!(ret.is_err_and(|e| e.to_string().contains("invalid stage 'missing'")));
+    // No stage is started when a later one is invalid
+    assert!(!tmpdir.path().join("started").exists());
+}
+
+#[test]
+fn pipeline_task_needs_stages() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let ret = pipeline(&[]).run("name", &pipeline_config(tmpdir.path(), Vec::new()));
+    assert!(ret.is_err_and(|e| e.to_string().contains("Pipeline 'name' has no stages.")));
+}
+
+//
+// Matrix tests:
+//
+
+fn matrix_task(parallel: bool) -> AlchemistTaskType {
+    let mut matrix = matrix::Matrix::new();
+    matrix.insert(
+        "target".to_string(),
+        vec!["x86_64".to_string(), "aarch64".to_string()],
+    );
+    matrix.insert(
+        "profile".to_string(),
+        vec!["dev".to_string(), "release".to_string()],
+    );
+    AlchemistBasicTask {
+        command: "sh".to_string(),
+        args: Some(vec![
+            "-c".to_string(),
+            "touch \"${target}-${profile}-$profile\"".to_string(),
+        ]),
+        matrix: Some(matrix),
+        matrix_parallel: Some(parallel),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[test]
+fn matrix_task_runs_every_combination() {
+    for parallel in [false, true] {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let config = pipeline_config(tmpdir.path(), Vec::new());
+        assert_eq!(matrix_task(parallel).run("build", &config), Result::Ok(()));
+
+        let mut files = std::fs::read_dir(tmpdir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "aarch64-dev-dev",
+                "aarch64-release-release",
+                "x86_64-dev-dev",
+                "x86_64-release-release",
+            ]
+        );
+    }
+}
+
+#[test]
+fn matrix_instance_can_be_referenced() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(tmpdir.path(), vec![("build", matrix_task(false))]);
+
+    let (task_config, instance) = config
+        .resolve_task("build[profile=release,target=aarch64]")
+        .unwrap()
+        .unwrap();
+    assert_eq!(instance.run("build", &task_config), Result::Ok(()));
+    assert!(tmpdir.path().join("aarch64-release-release").exists());
+
+    assert!(
+        config
+            .resolve_task("build[profile=debug,target=aarch64]")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn matrix_instances_are_in_the_junit_report() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let config = pipeline_config(tmpdir.path(), Vec::new());
+    assert_eq!(
+        matrix_task(false).run("junit_matrix", &config),
+        Result::Ok(())
+    );
+
+    let report = tmpdir.path().join("junit.xml");
+    report::write_junit(&report).unwrap();
+    let xml = std::fs::read_to_string(report).unwrap();
+    assert!(xml.contains(r#"<testcase name="junit_matrix" classname="alchemist""#));
+    for instance in [
+        "junit_matrix[target=x86_64,profile=dev]",
+        "junit_matrix[target=aarch64,profile=release]",
+    ] {
+        assert!(
+            xml.contains(&format!(
+                r#"<testcase name="{instance}" classname="alchemist""#
+            )),
+            "{xml}"
+        );
+    }
+}
+
+#[test]
+fn env_is_serialized_in_order() {
+    let task = AlchemistBasicTask {
+        command: "true".to_string(),
+        env: Some(
+            ["D", "B", "A", "C", "E"]
+                .iter()
+                .map(|key| (key.to_string(), key.to_lowercase()))
+                .collect(),
+        ),
+        ..Default::default()
+    };
+    assert_eq!(
+        toml::to_string(&task).unwrap(),
+        "command = \"true\"\n\n[env]\nA = \"a\"\nB = \"b\"\nC = \"c\"\nD = \"d\"\nE = \"e\"\n"
+    );
+}
@@ -0,0 +1,191 @@
+#[cfg(test)]
+#[path = "watch_test.rs"]
+mod watch_test;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::cli::terminal;
+use crate::error::{AssertionError, Result};
+use crate::process;
+
+/// Changes within this time of each other only cause a single re-run
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// How often to check whether the current run has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Decides which changed files should re-run the watched tasks
+pub struct WatchFilter {
+    root: PathBuf,
+    patterns: GlobSet,
+    gitignores: Option<Gitignores>,
+}
+
+/// The ignore rules of git for the files under a root: the `.gitignore` of
+/// every directory and `.git/info/exclude`
+struct Gitignores {
+    exclude: Gitignore,
+    /// The `.gitignore` of each directory, read when a file in it first changes
+    by_dir: Mutex<HashMap<PathBuf, Gitignore>>,
+}
+
+impl Gitignores {
+    fn new(root: &Path) -> Self {
+        Self {
+            exclude: read_ignore_file(root, &root.join(".git").join("info").join("exclude")),
+            by_dir: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `path` is ignored, the `.gitignore` closest to it taking precedence
+    fn is_ignored(&self, root: &Path, path: &Path) -> bool {
+        let is_dir = path.is_dir();
+        let mut by_dir = self.by_dir.lock().expect("gitignores poisoned");
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(root) {
+                break;
+            }
+            let gitignore = by_dir
+                .entry(dir.to_path_buf())
+                .or_insert_with(|| read_ignore_file(dir, &dir.join(".gitignore")));
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        self.exclude
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
+
+/// The rules of the ignore file at `path` for the files under `dir`, none when it does not exist
+fn read_ignore_file(dir: &Path, path: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    // A missing file is fine, invalid lines are skipped.
+    let _ = builder.add(path);
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+impl WatchFilter {
+    /// Matches files under `root` against `patterns` (all files when empty)
+    ///
+    /// When `respect_gitignore` is set, files git ignores (by a `.gitignore`
+    /// in `root` or below, or by `.git/info/exclude`) never match.
+    pub fn new(root: &Path, patterns: &[String], respect_gitignore: bool) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).map_err(|e| {
+                AssertionError(format!("Invalid watch pattern '{pattern}' ({e})."))
+            })?);
+        }
+        let patterns = builder
+            .build()
+            .map_err(|e| AssertionError(format!("Invalid watch patterns ({e}).")))?;
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            patterns,
+            gitignores: respect_gitignore.then(|| Gitignores::new(root)),
+        })
+    }
+
+    pub fn is_relevant(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if relative.components().any(|c| c.as_os_str() == ".git") {
+            return false;
+        }
+        if let Some(gitignores) = &self.gitignores
+            && gitignores.is_ignored(&self.root, path)
+        {
+            return false;
+        }
+        self.patterns.is_empty() || self.patterns.is_match(relative)
+    }
+}
+
+fn report(result: Result<()>) {
+    match result {
+        Ok(_) => terminal::ok("Finished running all given tasks."),
+        Err(e) => terminal::error(e),
+    }
+    terminal::info("Waiting for changes...");
+}
+
+/// Calls `run` and calls it again every time a relevant file changes
+///
+/// A run that is still going when files change is killed first. This only
+/// returns when the files can no longer be watched.
+pub fn watch<F: Fn() -> Result<()> + Sync>(filter: WatchFilter, run: F) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| AssertionError(format!("Can not watch for file changes ({e}).")))?;
+    watcher
+        .watch(&filter.root, RecursiveMode::Recursive)
+        .map_err(|e| {
+            AssertionError(format!(
+                "Can not watch '{}' for changes ({e}).",
+                filter.root.display()
+            ))
+        })?;
+
+    std::thread::scope(|scope| {
+        let mut run_number = 1;
+        loop {
+            let mut current_run = Some(scope.spawn(&run));
+
+            // Wait for a relevant change, reporting the run once it finishes.
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(Ok(event)) => {
+                        if !matches!(event.kind, EventKind::Access(_))
+                            && event.paths.iter().any(|p| filter.is_relevant(p))
+                        {
+                            break;
+                        }
+                    }
+                    Ok(Err(e)) => terminal::warn(format!("Error while watching files: {e}")),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return AssertionError("Stopped watching for file changes.".into()).into();
+                    }
+                }
+                if current_run.as_ref().is_some_and(|r| r.is_finished()) {
+                    report(
+                        current_run
+                            .take()
+                            .unwrap()
+                            .join()
+                            .expect("Can not join thread"),
+                    );
+                }
+            }
+            while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+
+            if let Some(previous_run) = current_run.take() {
+                terminal::warn("Files changed, stopping the previous run.");
+                process::cancel(libc::SIGKILL);
+                let _ = previous_run.join();
+                process::reset_cancelled();
+            }
+            run_number += 1;
+            terminal::info(format!(
+                "{} Files changed, starting run #{} {}",
+                "─".repeat(8),
+                run_number,
+                "─".repeat(8)
+            ));
+        }
+    })
+}
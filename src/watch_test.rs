@@ -0,0 +1,55 @@
+use super::*;
+
+#[test]
+fn watch_filter_patterns() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path();
+    let filter = WatchFilter::new(root, &["src/**/*.rs".to_string()], false).unwrap();
+
+    assert!(filter.is_relevant(&root.join("src/main.rs")));
+    assert!(filter.is_relevant(&root.join("src/cli/interface.rs")));
+    assert!(!filter.is_relevant(&root.join("README.md")));
+    assert!(!filter.is_relevant(Path::new("/somewhere/else/src/main.rs")));
+}
+
+#[test]
+fn watch_filter_respects_gitignore() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path();
+    std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+
+    let filter = WatchFilter::new(root, &[], true).unwrap();
+    assert!(filter.is_relevant(&root.join("src/main.rs")));
+    assert!(!filter.is_relevant(&root.join("target/debug/alchemist")));
+    assert!(!filter.is_relevant(&root.join("build.log")));
+    assert!(!filter.is_relevant(&root.join(".git/index")));
+
+    let filter = WatchFilter::new(root, &[], false).unwrap();
+    assert!(filter.is_relevant(&root.join("build.log")));
+}
+
+#[test]
+fn watch_filter_invalid_pattern() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    assert!(WatchFilter::new(tmpdir.path(), &["src/[".to_string()], true).is_err());
+}
+
+#[test]
+fn watch_filter_respects_nested_gitignores_and_excludes() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path();
+    std::fs::create_dir_all(root.join("app/dist")).unwrap();
+    std::fs::create_dir_all(root.join(".git/info")).unwrap();
+    std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+    std::fs::write(root.join("app/.gitignore"), "dist/\n!keep.log\n").unwrap();
+    std::fs::write(root.join(".git/info/exclude"), "notes.txt\n").unwrap();
+
+    let filter = WatchFilter::new(root, &[], true).unwrap();
+    assert!(filter.is_relevant(&root.join("app/src/main.rs")));
+    assert!(!filter.is_relevant(&root.join("app/dist/bundle.js")));
+    assert!(filter.is_relevant(&root.join("dist/bundle.js")));
+    assert!(!filter.is_relevant(&root.join("app/debug.log")));
+    assert!(filter.is_relevant(&root.join("app/keep.log")));
+    assert!(!filter.is_relevant(&root.join("notes.txt")));
+    assert!(!filter.is_relevant(&root.join("app/notes.txt")));
+}
@@ -0,0 +1,29 @@
+use std::process::Command;
+
+#[test]
+fn completions_on_stdout_are_only_the_script() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        tmpdir.path().join("alchemist.toml"),
+        "[tasks.build]\ncommand = \"true\"\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_alchemist"))
+        .args(["-s", "bash", "--stdout"])
+        .current_dir(tmpdir.path())
+        .env_remove("COMPLETE")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        script
+            .trim_start()
+            .starts_with("_clap_complete_alchemist()"),
+        "{script}"
+    );
+    assert!(!script.contains("alchemist version"), "{script}");
+    assert!(!script.contains("Using alchemist file"), "{script}");
+}
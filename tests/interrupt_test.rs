@@ -0,0 +1,44 @@
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[test]
+fn interrupted_runs_write_the_junit_report() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        tmpdir.path().join("alchemist.toml"),
+        "[tasks.ci]\nserial_tasks = [\"build\", \"test\"]\n\n[tasks.build]\nshell_script = \"true\"\n\n[tasks.test]\nshell_script = \"touch started; sleep 30\"\n",
+    )
+    .unwrap();
+    let report = tmpdir.path().join("junit.xml");
+    let mut alchemist = Command::new(env!("CARGO_BIN_EXE_alchemist"))
+        .args(["-q", "--report-junit"])
+        .arg(&report)
+        .arg("ci")
+        .current_dir(tmpdir.path())
+        .env("XDG_CONFIG_HOME", tmpdir.path())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while !tmpdir.path().join("started").exists() && started.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // SAFETY: kill(2) has no memory safety requirements.
+    unsafe {
+        libc::kill(alchemist.id() as libc::pid_t, libc::SIGTERM);
+    }
+    let status = alchemist.wait().unwrap();
+    assert_eq!(status.code(), Some(128 + libc::SIGTERM));
+
+    let xml = std::fs::read_to_string(report).unwrap();
+    assert!(
+        xml.contains(r#"<testcase name="build" classname="ci""#),
+        "{xml}"
+    );
+    assert!(
+        xml.contains(r#"<testcase name="test" classname="ci""#)
+            && xml.contains(r#"<error message="cancelled">"#),
+        "{xml}"
+    );
+}
@@ -0,0 +1,29 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn config_from_stdin_runs_the_default_task() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let mut alchemist = Command::new(env!("CARGO_BIN_EXE_alchemist"))
+        .args(["-q", "-f", "-"])
+        .current_dir(tmpdir.path())
+        .env("XDG_CONFIG_HOME", tmpdir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    alchemist
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            b"default = \"hello\"\n\n[tasks.hello]\nshell_script = \"echo hello from stdin\"\n",
+        )
+        .unwrap();
+    let output = alchemist.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.contains("hello from stdin"), "{output}");
+    assert!(!output.contains("does not exist"), "{output}");
+}
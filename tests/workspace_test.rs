@@ -0,0 +1,35 @@
+use std::process::Command;
+
+#[test]
+fn workspace_members_get_global_tasks() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let root = tmpdir.path().join("project");
+    std::fs::create_dir_all(root.join("member")).unwrap();
+    std::fs::create_dir_all(tmpdir.path().join("config/alchemist")).unwrap();
+    std::fs::write(
+        root.join("alchemist.toml"),
+        "workspace = [\"member\"]\n[tasks]\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("member/alchemist.toml"), "[tasks]\n").unwrap();
+    std::fs::write(
+        tmpdir.path().join("config/alchemist/alchemist.toml"),
+        "[tasks.hello]\nshell_script = \"echo global hello\"\n",
+    )
+    .unwrap();
+
+    let run = |no_global: bool| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_alchemist"));
+        cmd.args(["-q", "--workspace", "hello"])
+            .current_dir(&root)
+            .env("XDG_CONFIG_HOME", tmpdir.path().join("config"));
+        if no_global {
+            cmd.arg("--no-global");
+        }
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(run(false).contains("global hello"));
+    assert!(!run(true).contains("global hello"));
+}